@@ -0,0 +1,195 @@
+// chunks are lit like a standard material but each vertex is packed into two u32s
+// and each face picks its texture from a layer of an array and its tint from a palette
+// the unpacking has to match `ChunkVertex::pack`
+
+#import bevy_pbr::{
+    mesh_functions,
+    view_transformations::position_world_to_clip,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    pbr_bindings::material,
+    prepass_io::FragmentOutput,
+}
+#ifdef MOTION_VECTOR_PREPASS
+#import bevy_pbr::pbr_prepass_functions::calculate_motion_vector
+#endif
+#else
+#import bevy_pbr::{
+    forward_io::{FragmentOutput, VertexOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+@group(2) @binding(100) var block_textures: texture_2d_array<f32>;
+@group(2) @binding(101) var block_sampler: sampler;
+@group(2) @binding(102) var<uniform> tints: array<vec4<f32>, 64>;
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) voxel: vec2<u32>,
+};
+
+// in `Direction` order
+const NORMALS = array<vec3<f32>, 6>(
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, -1.0, 0.0),
+    vec3<f32>(-1.0, 0.0, 0.0),
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 0.0, -1.0),
+);
+
+fn unpack_position(voxel: vec2<u32>) -> vec3<f32> {
+    let steps = vec3<u32>(voxel.x, voxel.x >> 9u, voxel.x >> 18u) & vec3<u32>(0x1ffu);
+    return vec3<f32>(steps) / 16.0 - 0.5;
+}
+
+fn unpack_normal(voxel: vec2<u32>) -> vec3<f32> {
+    return NORMALS[min((voxel.x >> 27u) & 7u, 5u)];
+}
+
+fn unpack_uv(voxel: vec2<u32>) -> vec2<f32> {
+    return vec2<f32>(vec2<u32>(voxel.y, voxel.y >> 5u) & vec2<u32>(0x1fu)) / 16.0;
+}
+
+fn unpack_layer(voxel: vec2<u32>) -> u32 {
+    return voxel.y >> 20u;
+}
+
+fn world_position(voxel: vec2<u32>, instance_index: u32) -> vec4<f32> {
+    let world_from_local = mesh_functions::get_world_from_local(instance_index);
+    return mesh_functions::mesh_position_local_to_world(world_from_local, vec4<f32>(unpack_position(voxel), 1.0));
+}
+
+#ifdef PREPASS_PIPELINE
+
+// depth, normals and shadows need the position and normal
+// and the uv and layer so cut out texels like the air around plants can be discarded
+struct ChunkPrepassOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @location(3) @interpolate(flat) layer: u32,
+#ifdef MOTION_VECTOR_PREPASS
+    @location(4) previous_world_position: vec4<f32>,
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    @location(5) clip_position_unclamped: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> ChunkPrepassOutput {
+    var out: ChunkPrepassOutput;
+    out.world_position = world_position(vertex.voxel, vertex.instance_index);
+    out.position = position_world_to_clip(out.world_position.xyz);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.position;
+    out.position.z = min(out.position.z, 1.0);
+#endif
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(unpack_normal(vertex.voxel), vertex.instance_index);
+    out.uv = unpack_uv(vertex.voxel);
+    out.layer = unpack_layer(vertex.voxel);
+#ifdef MOTION_VECTOR_PREPASS
+    let previous_world_from_local = mesh_functions::get_previous_world_from_local(vertex.instance_index);
+    out.previous_world_position = mesh_functions::mesh_position_local_to_world(previous_world_from_local, vec4<f32>(unpack_position(vertex.voxel), 1.0));
+#endif
+    return out;
+}
+
+// the same cutoff as the main pass so see through texels don't write depth or cast shadows
+fn discard_cut_out(in: ChunkPrepassOutput) {
+    let alpha = textureSample(block_textures, block_sampler, in.uv, in.layer).a * material.base_color.a;
+    if alpha < material.alpha_cutoff {
+        discard;
+    }
+}
+
+#ifdef PREPASS_FRAGMENT
+@fragment
+fn fragment(in: ChunkPrepassOutput) -> FragmentOutput {
+    discard_cut_out(in);
+    var out: FragmentOutput;
+#ifdef NORMAL_PREPASS
+    out.normal = vec4<f32>(normalize(in.world_normal) * 0.5 + 0.5, 1.0);
+#endif
+#ifdef MOTION_VECTOR_PREPASS
+    out.motion_vector = calculate_motion_vector(in.world_position, in.previous_world_position);
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    out.frag_depth = in.clip_position_unclamped.z;
+#endif
+    return out;
+}
+#else
+@fragment
+fn fragment(in: ChunkPrepassOutput) {
+    discard_cut_out(in);
+}
+#endif
+
+#else
+
+struct ChunkVertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    // darkening from ambient occlusion
+    @location(3) shade: f32,
+    @location(4) @interpolate(flat) tint: vec4<f32>,
+    @location(5) @interpolate(flat) light: f32,
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    @location(6) @interpolate(flat) instance_index: u32,
+#endif
+    @location(8) @interpolate(flat) layer: u32,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> ChunkVertexOutput {
+    var out: ChunkVertexOutput;
+    out.world_position = world_position(vertex.voxel, vertex.instance_index);
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(unpack_normal(vertex.voxel), vertex.instance_index);
+    out.uv = unpack_uv(vertex.voxel);
+    out.shade = 1.0 - 0.2 * f32(vertex.voxel.x >> 30u);
+    out.light = f32((vertex.voxel.y >> 10u) & 0xfu) / 15.0;
+    out.tint = tints[(vertex.voxel.y >> 14u) & 0x3fu];
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = vertex.instance_index;
+#endif
+    out.layer = unpack_layer(vertex.voxel);
+    return out;
+}
+
+@fragment
+fn fragment(in: ChunkVertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    // the standard material code only knows about its own vertex output
+    var standard: VertexOutput;
+    standard.position = in.position;
+    standard.world_position = in.world_position;
+    standard.world_normal = in.world_normal;
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    standard.instance_index = in.instance_index;
+#endif
+
+    var pbr_input = pbr_input_from_standard_material(standard, is_front);
+    let texel = textureSample(block_textures, block_sampler, in.uv, in.layer);
+    pbr_input.material.base_color *= texel * in.tint;
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+    pbr_input.material.base_color = vec4<f32>(pbr_input.material.base_color.rgb * in.shade, pbr_input.material.base_color.a);
+    // glowing blocks light their own faces
+    pbr_input.material.emissive = vec4<f32>(pbr_input.material.base_color.rgb * in.light, 1.0);
+
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    // this is where the camera's distance fog is blended in
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
+
+#endif
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::mouse::{AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::cam::MovementSettings;
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputMap>();
+    }
+}
+
+/// Everything the player can do with a button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    /// jump when walking
+    MoveAscend,
+    MoveDescend,
+    ToggleGrabCursor,
+    Break,
+    Place,
+    /// copy the targeted block into the hotbar in creative
+    PickBlock,
+    Hotbar1,
+    Hotbar2,
+    Hotbar3,
+    Hotbar4,
+    Hotbar5,
+    Hotbar6,
+    Hotbar7,
+    Hotbar8,
+    Hotbar9,
+    HotbarNext,
+    HotbarPrevious,
+    CycleGameMode,
+    ViewDistanceUp,
+    ViewDistanceDown,
+    /// switch to the next pack in the resourcepacks directory
+    NextResourcePack,
+    ToggleDebug,
+    /// held while pressing [`Action::ToggleDebug`] to draw colliders
+    DebugColliders,
+    /// held while pressing [`Action::ToggleDebug`] to draw velocities
+    DebugVelocity,
+    /// hold to charge up then release to launch the player the way they are looking
+    DebugLaunch,
+}
+
+impl Action {
+    pub const HOTBAR: [Action; 9] = [
+        Action::Hotbar1,
+        Action::Hotbar2,
+        Action::Hotbar3,
+        Action::Hotbar4,
+        Action::Hotbar5,
+        Action::Hotbar6,
+        Action::Hotbar7,
+        Action::Hotbar8,
+        Action::Hotbar9,
+    ];
+}
+
+/// Something that can be pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+/// The bindings for every [`Action`], an action can have any number of bindings
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputMap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        let mut map = InputMap(BTreeMap::new());
+        map.bind(Action::MoveForward, Key(KeyCode::KeyW))
+            .bind(Action::MoveBackward, Key(KeyCode::KeyS))
+            .bind(Action::MoveLeft, Key(KeyCode::KeyA))
+            .bind(Action::MoveRight, Key(KeyCode::KeyD))
+            .bind(Action::MoveAscend, Key(KeyCode::Space))
+            .bind(Action::MoveAscend, Gamepad(GamepadButton::South))
+            .bind(Action::MoveDescend, Key(KeyCode::ShiftLeft))
+            .bind(Action::MoveDescend, Gamepad(GamepadButton::East))
+            .bind(Action::ToggleGrabCursor, Key(KeyCode::Escape))
+            .bind(Action::ToggleGrabCursor, Gamepad(GamepadButton::Start))
+            .bind(Action::Break, Mouse(MouseButton::Left))
+            .bind(Action::Break, Gamepad(GamepadButton::RightTrigger2))
+            .bind(Action::Place, Mouse(MouseButton::Right))
+            .bind(Action::Place, Gamepad(GamepadButton::LeftTrigger2))
+            .bind(Action::PickBlock, Mouse(MouseButton::Middle))
+            .bind(Action::PickBlock, Gamepad(GamepadButton::North))
+            .bind(Action::HotbarNext, Gamepad(GamepadButton::RightTrigger))
+            .bind(Action::HotbarPrevious, Gamepad(GamepadButton::LeftTrigger))
+            .bind(Action::CycleGameMode, Key(KeyCode::F12))
+            .bind(Action::ViewDistanceUp, Key(KeyCode::NumpadAdd))
+            .bind(Action::ViewDistanceDown, Key(KeyCode::NumpadSubtract))
+            .bind(Action::NextResourcePack, Key(KeyCode::F8))
+            .bind(Action::ToggleDebug, Key(KeyCode::F3))
+            .bind(Action::DebugColliders, Key(KeyCode::KeyC))
+            .bind(Action::DebugVelocity, Key(KeyCode::KeyV))
+            .bind(Action::DebugLaunch, Key(KeyCode::KeyL));
+        let digits = [
+            KeyCode::Digit1,
+            KeyCode::Digit2,
+            KeyCode::Digit3,
+            KeyCode::Digit4,
+            KeyCode::Digit5,
+            KeyCode::Digit6,
+            KeyCode::Digit7,
+            KeyCode::Digit8,
+            KeyCode::Digit9,
+        ];
+        for (action, key) in Action::HOTBAR.into_iter().zip(digits) {
+            map.bind(action, Key(key));
+        }
+        map
+    }
+}
+
+impl InputMap {
+    /// adds another binding to action
+    pub fn bind(&mut self, action: Action, binding: Binding) -> &mut Self {
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        self
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: Binding) -> &mut Self {
+        if let Some(bindings) = self.0.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+        self
+    }
+
+    /// removes every binding from action
+    pub fn clear(&mut self, action: Action) -> &mut Self {
+        // keep an empty entry so the defaults don't come back on load
+        self.0.insert(action, Vec::new());
+        self
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// fills in the default bindings for any action that is missing
+    /// so a settings file from an older version still gets new actions
+    pub fn with_defaults(self) -> InputMap {
+        let mut map = InputMap::default();
+        map.0.extend(self.0);
+        map
+    }
+}
+
+/// how many pixels a touchpad has to scroll to count as one notch of a mouse wheel
+const PIXELS_PER_LINE: f32 = 20.;
+
+/// Checks the state of [`Action`]s using the bindings in the [`InputMap`]
+#[derive(SystemParam)]
+pub struct Actions<'w, 's> {
+    map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    scroll: Res<'w, AccumulatedMouseScroll>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl Actions<'_, '_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.keys.pressed(*key),
+                Binding::Mouse(button) => self.mouse.pressed(*button),
+                Binding::Gamepad(button) => self.gamepads.iter().any(|pad| pad.pressed(*button)),
+            })
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.keys.just_pressed(*key),
+                Binding::Mouse(button) => self.mouse.just_pressed(*button),
+                Binding::Gamepad(button) => {
+                    self.gamepads.iter().any(|pad| pad.just_pressed(*button))
+                }
+            })
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.map
+            .bindings(action)
+            .iter()
+            .any(|binding| match binding {
+                Binding::Key(key) => self.keys.just_released(*key),
+                Binding::Mouse(button) => self.mouse.just_released(*button),
+                Binding::Gamepad(button) => {
+                    self.gamepads.iter().any(|pad| pad.just_released(*button))
+                }
+            })
+    }
+
+    /// which way the player wants to walk, x is right and y is forward
+    /// combines the movement keys with the left stick of every gamepad
+    pub fn movement(&self, settings: &MovementSettings) -> Vec2 {
+        let mut movement = Vec2::ZERO;
+        if self.pressed(Action::MoveForward) {
+            movement.y += 1.;
+        }
+        if self.pressed(Action::MoveBackward) {
+            movement.y -= 1.;
+        }
+        if self.pressed(Action::MoveLeft) {
+            movement.x -= 1.;
+        }
+        if self.pressed(Action::MoveRight) {
+            movement.x += 1.;
+        }
+        let stick = self.gamepads.iter().map(Gamepad::left_stick).sum();
+        (movement + settings.shape_stick(stick)).clamp_length_max(1.)
+    }
+
+    /// how the right sticks want to turn the camera, x is right and y is up
+    pub fn look(&self, settings: &MovementSettings) -> Vec2 {
+        let stick = self.gamepads.iter().map(Gamepad::right_stick).sum();
+        settings.shape_stick(stick)
+    }
+
+    /// how far the mouse wheel moved this frame in notches, positive is up
+    /// touchpads scroll in pixels so this can be a fraction of a notch
+    pub fn scroll(&self) -> f32 {
+        match self.scroll.unit {
+            MouseScrollUnit::Line => self.scroll.delta.y,
+            MouseScrollUnit::Pixel => self.scroll.delta.y / PIXELS_PER_LINE,
+        }
+    }
+}
+
+#[test]
+fn test_input_map_round_trip() {
+    let mut map = InputMap::default();
+    map.clear(Action::MoveForward)
+        .bind(Action::MoveForward, Binding::Key(KeyCode::ArrowUp))
+        .bind(Action::MoveForward, Binding::Gamepad(GamepadButton::DPadUp))
+        .unbind(Action::Break, Binding::Mouse(MouseButton::Left))
+        .unbind(
+            Action::Break,
+            Binding::Gamepad(GamepadButton::RightTrigger2),
+        );
+    let file = ron::ser::to_string_pretty(&map, ron::ser::PrettyConfig::default()).unwrap();
+    let loaded: InputMap = ron::from_str(&file).unwrap();
+    assert_eq!(loaded, map);
+    assert_eq!(
+        loaded.bindings(Action::MoveForward),
+        [
+            Binding::Key(KeyCode::ArrowUp),
+            Binding::Gamepad(GamepadButton::DPadUp)
+        ]
+    );
+    assert!(loaded.bindings(Action::Break).is_empty());
+    // cleared actions stay cleared when the defaults are filled in on load
+    assert!(loaded
+        .clone()
+        .with_defaults()
+        .bindings(Action::Break)
+        .is_empty());
+    let mut cleared = InputMap::default();
+    cleared.clear(Action::PickBlock);
+    assert!(cleared
+        .with_defaults()
+        .bindings(Action::PickBlock)
+        .is_empty());
+}
+
+#[test]
+fn test_default_bindings_are_unique() {
+    let map = InputMap::default();
+    let mut seen = std::collections::HashMap::new();
+    for (action, bindings) in map.0.iter() {
+        for binding in bindings {
+            if let Some(other) = seen.insert(*binding, *action) {
+                panic!(
+                    "{:?} is bound to both {:?} and {:?}",
+                    binding, other, action
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_gamepad_actions() {
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::input::{
+        gamepad::{
+            GamepadConnection, GamepadConnectionEvent, RawGamepadAxisChangedEvent,
+            RawGamepadButtonChangedEvent, RawGamepadEvent,
+        },
+        InputPlugin,
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin))
+        .init_resource::<InputMap>()
+        .init_resource::<MovementSettings>();
+    let pad = app.world_mut().spawn_empty().id();
+    app.world_mut()
+        .send_event(RawGamepadEvent::Connection(GamepadConnectionEvent::new(
+            pad,
+            GamepadConnection::Connected {
+                name: "Test Pad".to_string(),
+                vendor_id: None,
+                product_id: None,
+            },
+        )));
+    app.update();
+
+    let events = [
+        RawGamepadEvent::Axis(RawGamepadAxisChangedEvent::new(
+            pad,
+            GamepadAxis::LeftStickY,
+            0.8,
+        )),
+        // inside the deadzone so it should be ignored
+        RawGamepadEvent::Axis(RawGamepadAxisChangedEvent::new(
+            pad,
+            GamepadAxis::RightStickX,
+            0.1,
+        )),
+        RawGamepadEvent::Button(RawGamepadButtonChangedEvent::new(
+            pad,
+            GamepadButton::RightTrigger2,
+            1.,
+        )),
+    ];
+    for event in events {
+        app.world_mut().send_event(event);
+    }
+    app.update();
+
+    let (movement, look, breaking, placing) = app
+        .world_mut()
+        .run_system_once(|actions: Actions, settings: Res<MovementSettings>| {
+            (
+                actions.movement(&settings),
+                actions.look(&settings),
+                actions.pressed(Action::Break),
+                actions.pressed(Action::Place),
+            )
+        })
+        .unwrap();
+    let expected = MovementSettings::default().shape_stick(Vec2::new(0., 0.8));
+    assert!(expected.y > 0. && expected.y < 0.8);
+    assert!((movement - expected).length() < 1e-4);
+    assert_eq!(look, Vec2::ZERO);
+    assert!(breaking);
+    assert!(!placing);
+}
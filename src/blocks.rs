@@ -1,37 +1,60 @@
 use crate::prelude::*;
-use bevy::{
-    prelude::*,
-    render::{mesh::VertexAttributeValues, render_asset::RenderAssetUsages},
-    utils::HashMap,
-};
-use std::ops::Add;
-
-#[derive(
-    Debug,
-    Default,
-    Clone,
-    Copy,
-    strum_macros::EnumIter,
-    strum_macros::EnumCount,
-    PartialEq,
-    Eq,
-    Hash,
-    strum_macros::FromRepr,
-)]
-pub enum BlockType {
-    #[default]
-    Air,
-    Bedrock,
-    Gravel,
-    Dirt,
-    Stone,
-    Sand,
-    GoldOre,
-    IronOre,
-    CoalOre,
-    // DeadBush,
-    Grass,
-    Water,
+use crate::textures::{ChunkVertex, ATTRIBUTE_VOXEL};
+use bevy::{prelude::*, render::render_asset::RenderAssetUsages, utils::HashMap};
+use std::{borrow::Cow, ops::Add};
+use strum::IntoEnumIterator;
+
+mod model;
+mod registry;
+mod state;
+
+pub use model::{BlockShape, FaceMask, ModelBox};
+pub use registry::{BlockFaces, BlockModel, BlockProperties, BlockRegistry};
+pub use state::{Axis, BlockState, Orientation};
+
+pub struct BlocksPlugin;
+
+impl Plugin for BlocksPlugin {
+    fn build(&self, _app: &mut App) {
+        // load the blocks up front so a broken file is reported at startup
+        BlockRegistry::global();
+    }
+}
+
+/// A kind of block, the number is its id in the [`BlockRegistry`]
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockType(u16);
+
+/// the blocks that are always registered, see [`BlockRegistry::default`]
+#[allow(non_upper_case_globals)]
+impl BlockType {
+    pub const Air: BlockType = BlockType(0);
+    pub const Bedrock: BlockType = BlockType(1);
+    pub const Gravel: BlockType = BlockType(2);
+    pub const Dirt: BlockType = BlockType(3);
+    pub const Stone: BlockType = BlockType(4);
+    pub const Sand: BlockType = BlockType(5);
+    pub const GoldOre: BlockType = BlockType(6);
+    pub const IronOre: BlockType = BlockType(7);
+    pub const CoalOre: BlockType = BlockType(8);
+    pub const Grass: BlockType = BlockType(9);
+    pub const Water: BlockType = BlockType(10);
+    pub const DeadBush: BlockType = BlockType(11);
+    pub const TallGrass: BlockType = BlockType(12);
+    pub const StoneSlab: BlockType = BlockType(13);
+    pub const StoneStairs: BlockType = BlockType(14);
+    pub const DirtPath: BlockType = BlockType(15);
+}
+
+impl std::fmt::Debug for BlockType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = &self.properties().name;
+        if *self != BlockType::Air && name == "Air" {
+            write!(f, "Unknown({})", self.0)
+        } else {
+            f.write_str(name)
+        }
+    }
 }
 
 #[derive(Debug, strum_macros::EnumIter, Clone, Copy, PartialEq)]
@@ -45,6 +68,50 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// the face a ray enters a block through when stepping along axis (0 = x, 1 = y, 2 = z) in the direction of step
+    pub const fn entered(axis: usize, step: i32) -> Direction {
+        match (axis, step > 0) {
+            (0, true) => Direction::Left,
+            (0, false) => Direction::Right,
+            (1, true) => Direction::Down,
+            (1, false) => Direction::Up,
+            (2, true) => Direction::Back,
+            _ => Direction::Forward,
+        }
+    }
+
+    /// the direction closest to v, ties go to y then z
+    pub fn from_vec(v: Vec3) -> Direction {
+        let abs = v.abs();
+        if abs.y >= abs.x && abs.y >= abs.z {
+            if v.y >= 0. {
+                Direction::Up
+            } else {
+                Direction::Down
+            }
+        } else if abs.z >= abs.x {
+            if v.z >= 0. {
+                Direction::Forward
+            } else {
+                Direction::Back
+            }
+        } else if v.x >= 0. {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    }
+
+    /// the step to the neighbor on this side
+    pub fn normal(&self) -> IVec3 {
+        IVec3::ZERO + *self
+    }
+
+    /// where the direction points after rotation
+    pub fn rotated(&self, rotation: Quat) -> Direction {
+        Direction::from_vec(rotation * self.normal().as_vec3())
+    }
+
     fn collider_iter(&self, x: i32, y: i32, z: i32) -> core::ops::Range<i32> {
         match self {
             Direction::Up | Direction::Down => z..CHUNK_SIZE,
@@ -166,92 +233,205 @@ impl Add<Direction> for IVec3 {
     }
 }
 
+/// Faces of a block that all point the same way
 pub struct MeshData {
-    pub pos: &'static [[f32; 3]],
+    /// which way the faces point, plants are lit as if they face up
+    pub face: Direction,
+    pub pos: Vec<[f32; 3]>,
     pub uv: Vec<[f32; 2]>,
-    pub color: &'static [[f32; 4]],
-    pub indices: &'static [u32],
+    /// index of the [`crate::textures::TintPalette`] colour the whole face is multiplied by
+    pub tint: u8,
+    /// texture array layer the whole face uses
+    pub layer: u32,
+    pub indices: Vec<u32>,
+    /// the corners are darkened by the blocks around them, plants are left bright
+    pub shaded: bool,
 }
 
-impl BlockType {
-    pub const fn is_transparent(&self) -> bool {
-        match self {
-            BlockType::Water | BlockType::Air => true,
-            _ => false,
+/// uvs of the four corners of a face in the order [`BlockType::block_face`] lists them
+const FACE_UV: [[f32; 2]; 4] = [[0., 1.], [1., 1.], [1., 0.], [0., 0.]];
+
+/// the two diagonals a [`BlockModel::Cross`] is drawn along, bottom corners first like [`FACE_UV`]
+const CROSS_QUADS: [[[f32; 3]; 4]; 2] = [
+    [
+        [-0.5, -0.5, -0.5],
+        [0.5, -0.5, 0.5],
+        [0.5, 0.5, 0.5],
+        [-0.5, 0.5, -0.5],
+    ],
+    [
+        [-0.5, -0.5, 0.5],
+        [0.5, -0.5, -0.5],
+        [0.5, 0.5, -0.5],
+        [-0.5, 0.5, 0.5],
+    ],
+];
+
+impl MeshData {
+    pub const EMPTY: MeshData = MeshData {
+        face: Direction::Up,
+        pos: Vec::new(),
+        uv: Vec::new(),
+        tint: 0,
+        layer: 0,
+        indices: Vec::new(),
+        shaded: true,
+    };
+
+    /// adds a quad wound so it is seen from the side the face points, double sided quads are seen from both
+    fn push_quad(&mut self, pos: [[f32; 3]; 4], uv: [[f32; 2]; 4], double_sided: bool) {
+        let start = self.pos.len() as u32;
+        self.pos.extend(pos);
+        self.uv.extend(uv);
+        self.indices.extend([0, 1, 2, 2, 3, 0].map(|i| i + start));
+        if double_sided {
+            self.indices.extend([2, 1, 0, 0, 3, 2].map(|i| i + start));
         }
     }
+}
 
-    pub const fn is_solid(&self) -> bool {
-        match self {
-            BlockType::Water | BlockType::Air => false,
-            _ => true,
+impl BlockType {
+    pub const fn from_id(id: u16) -> BlockType {
+        BlockType(id)
+    }
+
+    pub const fn id(&self) -> u16 {
+        self.0
+    }
+
+    /// every registered block
+    pub fn iter() -> impl Iterator<Item = BlockType> {
+        (0..BlockRegistry::global().len() as u16).map(BlockType)
+    }
+
+    pub fn properties(&self) -> &'static BlockProperties {
+        BlockRegistry::global().get(*self)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.properties().transparent
+    }
+
+    pub fn is_solid(&self) -> bool {
+        self.properties().solid
+    }
+
+    /// how many seconds it takes to break the block in survival
+    pub fn hardness(&self) -> f32 {
+        self.properties().hardness.unwrap_or(f32::INFINITY)
+    }
+
+    /// blocks that fall when there is nothing under them
+    pub fn has_gravity(&self) -> bool {
+        self.properties().gravity
+    }
+
+    /// path to the texture on a face of the block
+    pub fn texture(&self, direction: Direction) -> Option<&'static str> {
+        let textures = self.properties().textures.as_ref()?;
+        Some(textures.get(direction).as_str())
+    }
+
+    /// colour multiplied into a face of the block
+    pub fn tint(&self, direction: Direction) -> [f32; 4] {
+        match &self.properties().tint {
+            Some(tint) => *tint.get(direction),
+            None => [1.; 4],
         }
     }
 
-    pub const fn get_texture_paths(&self) -> &'static [&'static str] {
-        match self {
-            BlockType::Air => &[],
-            BlockType::Bedrock => &["PureBDcraft/textures/block/bedrock.png"],
-            BlockType::Gravel => &["PureBDcraft/textures/block/gravel.png"],
-            BlockType::Dirt => &["PureBDcraft/textures/block/dirt.png"],
-            BlockType::Stone => &["PureBDcraft/textures/block/stone.png"],
-            BlockType::Sand => &["PureBDcraft/textures/block/sand.png"],
-            BlockType::GoldOre => &["PureBDcraft/textures/block/gold_ore.png"],
-            BlockType::IronOre => &["PureBDcraft/textures/block/iron_ore.png"],
-            BlockType::CoalOre => &["PureBDcraft/textures/block/coal_ore.png"],
-            // BlockType::DeadBush => &["PureBDcraft/textures/block/dead_bush.png"],
-            BlockType::Grass => &[
-                "PureBDcraft/textures/block/grass_block_top.png",
-                "PureBDcraft/textures/block/grass_block_side.png",
-                "PureBDcraft/textures/block/dirt.png",
-            ],
-            BlockType::Water => &[],
+    pub fn get_icon_paths(&self) -> &'static str {
+        self.properties().icon.as_deref().unwrap_or("")
+    }
+
+    /// how the block is drawn and collided with
+    pub fn shape(&self) -> &'static BlockShape {
+        BlockRegistry::global().shape(*self)
+    }
+
+    /// the shape of the block turned and drained to match its state
+    pub fn state_shape(&self, state: BlockState) -> Cow<'static, BlockShape> {
+        let level = state.fluid_level();
+        let mut shape = if level > 0 && self.properties().model == BlockModel::Liquid {
+            Cow::Owned(BlockShape::drained(level))
+        } else {
+            Cow::Borrowed(self.shape())
+        };
+        let rotation = state.rotation();
+        if rotation != Quat::IDENTITY {
+            shape = Cow::Owned(shape.rotated(rotation));
         }
+        shape
     }
 
-    pub const fn get_icon_paths(&self) -> &'static str {
-        match self {
-            BlockType::Air => "",
-            BlockType::Bedrock => "PureBDcraft/textures/block/bedrock.png",
-            BlockType::Gravel => "PureBDcraft/textures/block/gravel.png",
-            BlockType::Dirt => "PureBDcraft/textures/block/dirt.png",
-            BlockType::Stone => "PureBDcraft/textures/block/stone.png",
-            BlockType::Sand => "PureBDcraft/textures/block/sand.png",
-            BlockType::GoldOre => "PureBDcraft/textures/block/gold_ore.png",
-            BlockType::IronOre => "PureBDcraft/textures/block/iron_ore.png",
-            BlockType::CoalOre => "PureBDcraft/textures/block/coal_ore.png",
-            // BlockType::DeadBush => "PureBDcraft/textures/block/dead_bush.png",
-            BlockType::Grass => "PureBDcraft/textures/block/grass_block_side.png",
-            BlockType::Water => "",
+    /// the boxes a ray can hit turned to match the state, plants can be hit but liquids can't
+    pub fn target_boxes(&self, state: BlockState) -> Cow<'static, [ModelBox]> {
+        if self.properties().model == BlockModel::Liquid {
+            return Cow::Borrowed(&[]);
+        }
+        match self.state_shape(state) {
+            Cow::Borrowed(shape) => Cow::Borrowed(shape.target_boxes()),
+            Cow::Owned(shape) => Cow::Owned(shape.target_boxes().to_vec()),
         }
     }
 
+    /// the faces of the block that can be seen past its neighbors, which are in [`Direction`] order
+    /// models are turned by the state, faces keep the texture of the side of the model they came from
     pub fn gen_mesh(
         &self,
-        direction: Direction,
+        state: BlockState,
+        neighbors: &[(BlockType, BlockState); 6],
         atlas_map: &crate::prelude::TextureHandles,
-    ) -> MeshData {
-        match self {
-            BlockType::Bedrock
-            | BlockType::Gravel
-            | BlockType::Dirt
-            | BlockType::Stone
-            | BlockType::Sand
-            | BlockType::GoldOre
-            | BlockType::IronOre
-            | BlockType::CoalOre => {
-                let index = atlas_map.get_indexes(self);
-                BlockType::block_mesh(direction, index[0], atlas_map.len())
+    ) -> Vec<MeshData> {
+        let rotation = state.rotation();
+        // the boxes before they are turned, in the same order as shape
+        let model = self.state_shape(BlockState::DEFAULT.with_fluid_level(state.fluid_level()));
+        let shape = self.state_shape(state);
+        let mut faces = Vec::new();
+        for model_direction in Direction::iter() {
+            let Some(layer) = atlas_map.get_face_index(self, model_direction) else {
+                continue;
+            };
+            let direction = model_direction.rotated(rotation);
+            let (neighbor, neighbor_state) = neighbors[direction as usize];
+            // faces between two of the same block like water are hidden
+            let hides = !neighbor.is_transparent() || neighbor == *self;
+            let neighbor_shape = neighbor.state_shape(neighbor_state);
+            let mut face = MeshData {
+                face: direction,
+                tint: atlas_map.get_face_tint(self, model_direction),
+                layer: layer as u32,
+                ..MeshData::EMPTY
+            };
+            for (model_box, world_box) in model.boxes.iter().zip(shape.boxes.iter()) {
+                if hides && world_box.hidden_by(direction, &neighbor_shape) {
+                    continue;
+                }
+                let model_pos = model_box.face(model_direction);
+                let pos = model_pos.map(|pos| rotate(rotation, pos));
+                let uv = model_pos.map(|pos| face_uv(model_direction, pos));
+                face.push_quad(pos, uv, false);
+            }
+            if !face.pos.is_empty() {
+                faces.push(face);
             }
-            // BlockType::DeadBush => todo!(),
-            BlockType::Grass => BlockType::grass_mesh(direction, atlas_map),
-            BlockType::Air | BlockType::Water => MeshData {
-                pos: &[],
-                uv: vec![],
-                color: &[],
-                indices: &[],
-            },
         }
+        if shape.cross {
+            if let Some(layer) = atlas_map.get_face_index(self, Direction::Forward) {
+                let mut face = MeshData {
+                    tint: atlas_map.get_face_tint(self, Direction::Forward),
+                    layer: layer as u32,
+                    shaded: false,
+                    ..MeshData::EMPTY
+                };
+                for quad in CROSS_QUADS {
+                    let pos = quad.map(|pos| rotate(rotation, pos));
+                    face.push_quad(pos, FACE_UV, true);
+                }
+                faces.push(face);
+            }
+        }
+        faces
     }
 
     pub fn water_mesh(
@@ -262,18 +442,21 @@ impl BlockType {
     ) -> MeshData {
         let indexes = atlas_map.get_indexes(&BlockType::Water);
         MeshData {
+            face: direction,
             pos: if top_air {
-                BlockType::water_face(direction)
+                BlockType::water_face(direction).to_vec()
             } else {
-                BlockType::block_face(direction)
+                BlockType::block_face(direction).to_vec()
             },
-            uv: BlockType::block_uv(indexes[0], atlas_map.len()),
-            color: &[[0.2, 0.2, 0.8, 0.25]; 4],
+            uv: BlockType::block_uv(),
+            tint: atlas_map.get_face_tint(&BlockType::Water, direction),
+            layer: indexes[0] as u32,
             indices: if facing_solid {
-                &[2, 1, 0, 0, 3, 2]
+                vec![2, 1, 0, 0, 3, 2]
             } else {
-                &[0, 1, 2, 2, 3, 0, 2, 1, 0, 0, 3, 2]
+                vec![0, 1, 2, 2, 3, 0, 2, 1, 0, 0, 3, 2]
             },
+            shaded: true,
         }
     }
 
@@ -327,52 +510,20 @@ impl BlockType {
         }
     }
 
-    fn grass_mesh(direction: Direction, atlas_map: &crate::prelude::TextureHandles) -> MeshData {
-        let indexes = atlas_map.get_indexes(&BlockType::Grass);
-        match direction {
-            Direction::Up => MeshData {
-                pos: BlockType::block_face(direction),
-                uv: BlockType::block_uv(indexes[0], atlas_map.len()),
-                color: &[[0.2, 0.8, 0.2, 1.]; 4],
-                indices: &[0, 1, 2, 2, 3, 0],
-            },
-            Direction::Down => MeshData {
-                pos: BlockType::block_face(direction),
-                uv: BlockType::block_uv(indexes[2], atlas_map.len()),
-                color: &[[1., 1., 1., 1.]; 4],
-                indices: &[0, 1, 2, 2, 3, 0],
-            },
-            _ => MeshData {
-                pos: BlockType::block_face(direction),
-                uv: BlockType::block_uv(indexes[1], atlas_map.len()),
-                color: &[[1., 1., 1., 1.]; 4],
-                indices: &[0, 1, 2, 2, 3, 0],
-            },
-        }
-    }
-
-    fn block_uv(block: usize, atlas_size: usize) -> Vec<[f32; 2]> {
-        let y = block / atlas_size;
-        let x = block - y * atlas_size;
-        let uv_off = 1.0 / atlas_size as f32;
-        let uv_x_0 = (x as f32 + 0.02) * uv_off;
-        let uv_x_1 = (x as f32 + 0.98) * uv_off;
-        let uv_y_0 = (y as f32 + 0.02) * uv_off;
-        let uv_y_1 = (y as f32 + 0.98) * uv_off;
-        vec![
-            [uv_x_0, uv_y_1],
-            [uv_x_1, uv_y_1],
-            [uv_x_1, uv_y_0],
-            [uv_x_0, uv_y_0],
-        ]
+    /// every face covers its whole texture layer
+    fn block_uv() -> Vec<[f32; 2]> {
+        FACE_UV.to_vec()
     }
 
-    fn block_mesh(direction: Direction, index_pos: usize, index_len: usize) -> MeshData {
+    fn block_mesh(direction: Direction, layer: u32) -> MeshData {
         MeshData {
-            pos: BlockType::block_face(direction),
-            uv: BlockType::block_uv(index_pos, index_len),
-            color: &[[1., 1., 1., 1.]; 4],
-            indices: &[0, 1, 2, 2, 3, 0],
+            face: direction,
+            pos: BlockType::block_face(direction).to_vec(),
+            uv: BlockType::block_uv(),
+            tint: 0,
+            layer,
+            indices: vec![0, 1, 2, 2, 3, 0],
+            shaded: true,
         }
     }
 
@@ -479,81 +630,52 @@ impl BlockType {
     }
 }
 
-pub fn make_test_block_mesh(block_index: usize, atlas_size: usize) -> Mesh {
-    assert!(block_index < atlas_size * atlas_size);
-    let y = block_index / atlas_size;
-    let x = block_index - y * atlas_size;
-    let uv_off = 1.0 / atlas_size as f32;
-    let uv_x_0 = (x as f32 + 0.02) * uv_off;
-    let uv_x_1 = (x as f32 + 0.98) * uv_off;
-    let uv_y_0 = (y as f32 + 0.02) * uv_off;
-    let uv_y_1 = (y as f32 + 0.98) * uv_off;
-    let mut uvs = Vec::with_capacity(24);
-    for _ in 0..6 {
-        uvs.extend([
-            [uv_x_0, uv_y_1],
-            [uv_x_1, uv_y_1],
-            [uv_x_1, uv_y_0],
-            [uv_x_0, uv_y_0],
-        ]);
+/// turns a point in a block about its center, snapped back to 16ths so edges still meet
+fn rotate(rotation: Quat, pos: [f32; 3]) -> [f32; 3] {
+    ((rotation * Vec3::from_array(pos) * 16.).round() / 16.).to_array()
+}
+
+/// where on its texture a point on a face of a block is, so faces smaller than a block aren't stretched
+fn face_uv(direction: Direction, [x, y, z]: [f32; 3]) -> [f32; 2] {
+    match direction {
+        Direction::Up => [0.5 - z, x + 0.5],
+        Direction::Down => [x + 0.5, 0.5 - z],
+        Direction::Left => [z + 0.5, 0.5 - y],
+        Direction::Right => [0.5 - z, 0.5 - y],
+        Direction::Forward => [x + 0.5, 0.5 - y],
+        Direction::Back => [0.5 - x, 0.5 - y],
+    }
+}
+
+/// a unit cube in the packed chunk vertex format so it can be drawn with the chunk material
+pub fn make_test_block_mesh(layer: u32) -> Mesh {
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for direction in Direction::iter() {
+        let face = BlockType::block_mesh(direction, layer);
+        indices.extend(face.indices.iter().map(|i| *i + vertices.len() as u32));
+        vertices.extend(face.pos.iter().zip(face.uv).map(|(pos, uv)| {
+            ChunkVertex {
+                position: Vec3::from_array(*pos),
+                face: direction,
+                uv: Vec2::from_array(uv),
+                ao: 0,
+                light: 0,
+                tint: face.tint,
+                layer: face.layer,
+            }
+            .pack()
+        }));
     }
     let mut mesh = Mesh::new(
         bevy::render::render_resource::PrimitiveTopology::TriangleList,
         RenderAssetUsages::all(),
     );
-    mesh.insert_attribute(
-        Mesh::ATTRIBUTE_POSITION,
-        VertexAttributeValues::Float32x3(get_test_vertexes()),
-    );
-    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::Float32x2(uvs));
-    mesh.insert_indices(bevy::render::mesh::Indices::U16(get_test_indices()));
+    mesh.insert_attribute(ATTRIBUTE_VOXEL, vertices);
+    mesh.insert_indices(bevy::render::mesh::Indices::U32(indices));
     mesh
 }
 
-fn get_test_indices() -> Vec<u16> {
-    vec![
-        0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4, 8, 9, 10, 10, 11, 8, 12, 13, 14, 14, 15, 12, 16, 17,
-        18, 18, 19, 16, 20, 21, 22, 22, 23, 20,
-    ]
-}
-
-fn get_test_vertexes() -> Vec<[f32; 3]> {
-    const SIZE_LENGTH: f32 = 1.0;
-    const HALF_LENGTH: f32 = SIZE_LENGTH / 2.0;
-    vec![
-        // Front face
-        [-HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH], // 0
-        [HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH],  // 1
-        [HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],   // 2
-        [-HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],  // 3
-        // Back face
-        [HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH],   // 4
-        [HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH],  // 5
-        [-HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH], // 6
-        [-HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH],  // 7
-        // Left face
-        [-HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH], // 8
-        [-HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH],  // 9
-        [-HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],   // 10
-        [-HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH],  // 11
-        // Right face
-        [HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],   // 12
-        [HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH],  // 13
-        [HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH], // 14
-        [HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH],  // 15
-        // Top face
-        [HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],   // 16
-        [HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH],  // 17
-        [-HALF_LENGTH, HALF_LENGTH, -HALF_LENGTH], // 18
-        [-HALF_LENGTH, HALF_LENGTH, HALF_LENGTH],  // 19
-        // Bottom face
-        [-HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH], // 20
-        [HALF_LENGTH, -HALF_LENGTH, -HALF_LENGTH],  // 21
-        [HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH],   // 22
-        [-HALF_LENGTH, -HALF_LENGTH, HALF_LENGTH],  // 23
-    ]
-}
-
 impl rand::prelude::Distribution<BlockType> for BlockType {
     fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> BlockType {
         // match rng.gen_range(0, 3) { // rand 0.5, 0.6, 0.7
@@ -572,3 +694,15 @@ impl rand::prelude::Distribution<BlockType> for BlockType {
         }
     }
 }
+
+#[test]
+fn test_face_uv() {
+    // full faces line up with the whole texture
+    for direction in Direction::iter() {
+        let uv = BlockType::block_face(direction)
+            .iter()
+            .map(|pos| face_uv(direction, *pos))
+            .collect::<Vec<_>>();
+        assert_eq!(uv, FACE_UV, "{:?}", direction);
+    }
+}
@@ -0,0 +1,267 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::{BlockModel, BlockState, Direction};
+
+/// where json models are looked for, relative to the working directory
+const MODELS_DIR: &str = "assets";
+
+/// An axis aligned box inside a block in 16ths, 0..=16 on each axis like the elements of a minecraft model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelBox {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+}
+
+impl ModelBox {
+    pub const FULL: ModelBox = ModelBox::new([0., 0., 0.], [16., 16., 16.]);
+
+    pub const fn new(from: [f32; 3], to: [f32; 3]) -> ModelBox {
+        ModelBox { from, to }
+    }
+
+    /// the corner nearest negative infinity relative to the center of the block
+    pub fn min(&self) -> Vec3 {
+        Vec3::from_array(self.from).min(Vec3::from_array(self.to)) / 16. - 0.5
+    }
+
+    pub fn max(&self) -> Vec3 {
+        Vec3::from_array(self.from).max(Vec3::from_array(self.to)) / 16. - 0.5
+    }
+
+    /// the four corners of a face in the same order as [`super::BlockType::block_face`]
+    pub fn face(&self, direction: Direction) -> [[f32; 3]; 4] {
+        let (min, max) = (self.min(), self.max());
+        let corners = super::BlockType::block_face(direction);
+        std::array::from_fn(|i| {
+            let corner = Vec3::from_array(corners[i]);
+            Vec3::select(corner.cmplt(Vec3::ZERO), min, max).to_array()
+        })
+    }
+
+    /// the box turned about the center of the block, rounded back to whole 16ths
+    pub fn rotated(&self, rotation: Quat) -> ModelBox {
+        let center = Vec3::splat(8.);
+        let from = rotation * (Vec3::from_array(self.from) - center) + center;
+        let to = rotation * (Vec3::from_array(self.to) - center) + center;
+        ModelBox::new(
+            from.min(to).round().to_array(),
+            from.max(to).round().to_array(),
+        )
+    }
+
+    /// true if the face is against the side of the block and the neighbor's model covers it
+    pub fn hidden_by(&self, direction: Direction, neighbor: &BlockShape) -> bool {
+        self.on_side(direction) && neighbor.side(direction.rev()).covers(&self.mask(direction))
+    }
+
+    /// whether the face is on the side of the block so a neighbor can hide it
+    fn on_side(&self, direction: Direction) -> bool {
+        let (min, max) = (self.min(), self.max());
+        match direction {
+            Direction::Up => max.y >= 0.5,
+            Direction::Down => min.y <= -0.5,
+            Direction::Left => min.x <= -0.5,
+            Direction::Right => max.x >= 0.5,
+            Direction::Forward => max.z >= 0.5,
+            Direction::Back => min.z <= -0.5,
+        }
+    }
+
+    /// the texels of the side of the block a face covers
+    fn mask(&self, direction: Direction) -> FaceMask {
+        let normal = (IVec3::ZERO + direction).abs();
+        let [a, b] = match normal.to_array() {
+            [1, _, _] => [1, 2],
+            [_, 1, _] => [0, 2],
+            _ => [0, 1],
+        };
+        let texel = |value: f32| (value.clamp(0., 16.).round() as usize).min(16);
+        let range = |axis: usize| {
+            let (from, to) = (self.from[axis], self.to[axis]);
+            texel(from.min(to))..texel(from.max(to))
+        };
+        let mut mask = FaceMask::EMPTY;
+        let columns = range(a);
+        for row in range(b) {
+            for column in columns.clone() {
+                mask.0[row] |= 1 << column;
+            }
+        }
+        mask
+    }
+}
+
+/// Which of the 16x16 texels on the side of a block are covered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaceMask([u16; 16]);
+
+impl FaceMask {
+    pub const EMPTY: FaceMask = FaceMask([0; 16]);
+    pub const FULL: FaceMask = FaceMask([u16::MAX; 16]);
+
+    /// true if every texel of other is also covered by this
+    pub fn covers(&self, other: &FaceMask) -> bool {
+        self.0
+            .iter()
+            .zip(other.0)
+            .all(|(this, other)| other & !this == 0)
+    }
+}
+
+/// The boxes a [`BlockModel`] is made of, worked out once when the block is registered
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockShape {
+    /// these are both drawn and collided with
+    pub boxes: Vec<ModelBox>,
+    /// two quads crossing diagonally through the block like a plant
+    pub cross: bool,
+    /// how much of each side of the block the boxes cover in [`Direction`] order
+    sides: [FaceMask; 6],
+}
+
+impl BlockShape {
+    pub fn new(model: &BlockModel) -> BlockShape {
+        let boxes = match model {
+            BlockModel::None | BlockModel::Cross => Vec::new(),
+            BlockModel::Cube | BlockModel::Liquid => vec![ModelBox::FULL],
+            BlockModel::Slab => vec![ModelBox::new([0., 0., 0.], [16., 8., 16.])],
+            // the tall half is at the back
+            BlockModel::Stairs => vec![
+                ModelBox::new([0., 0., 0.], [16., 8., 16.]),
+                ModelBox::new([0., 8., 0.], [16., 16., 8.]),
+            ],
+            BlockModel::Boxes(boxes) => boxes.clone(),
+            BlockModel::Json(path) => match load_json(Path::new(MODELS_DIR).join(path)) {
+                Ok(boxes) => boxes,
+                Err(e) => {
+                    error!("Failed to load model {}: {}", path, e);
+                    vec![ModelBox::FULL]
+                }
+            },
+        };
+        BlockShape::from_boxes(boxes, matches!(model, BlockModel::Cross))
+    }
+
+    fn from_boxes(boxes: Vec<ModelBox>, cross: bool) -> BlockShape {
+        let mut sides = [FaceMask::EMPTY; 6];
+        for direction in Direction::iter() {
+            for model_box in boxes.iter().filter(|b| b.on_side(direction)) {
+                let mask = model_box.mask(direction);
+                for (side, mask) in sides[direction as usize].0.iter_mut().zip(mask.0) {
+                    *side |= mask;
+                }
+            }
+        }
+        BlockShape {
+            boxes,
+            cross,
+            sides,
+        }
+    }
+
+    /// the shape turned about the center of the block, the boxes stay in the same order
+    pub fn rotated(&self, rotation: Quat) -> BlockShape {
+        let boxes = self.boxes.iter().map(|b| b.rotated(rotation)).collect();
+        BlockShape::from_boxes(boxes, self.cross)
+    }
+
+    /// a liquid drained down to level, see [`super::BlockState::fluid_level`]
+    pub fn drained(level: u8) -> BlockShape {
+        let top = 16. - level.min(BlockState::MAX_FLUID_LEVEL) as f32 * 2.;
+        BlockShape::from_boxes(vec![ModelBox::new([0., 0., 0.], [16., top, 16.])], false)
+    }
+
+    /// the boxes a ray can hit, a plant is hit by a box around its quads so the air around it is passed through
+    pub fn target_boxes(&self) -> &[ModelBox] {
+        const PLANT: &[ModelBox] = &[ModelBox::new([2., 0., 2.], [14., 13., 14.])];
+        if self.cross {
+            PLANT
+        } else {
+            &self.boxes
+        }
+    }
+
+    /// how much of the side of the block facing direction is covered
+    pub fn side(&self, direction: Direction) -> &FaceMask {
+        &self.sides[direction as usize]
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The part of a minecraft style json model that gives its shape
+#[derive(Deserialize)]
+struct JsonModel {
+    #[serde(default)]
+    elements: Vec<ModelBox>,
+}
+
+/// reads the elements of a json model as boxes, anything else in the file is ignored
+pub fn load_json(path: impl AsRef<Path>) -> Result<Vec<ModelBox>, ModelError> {
+    let file = std::fs::read(path)?;
+    Ok(serde_json::from_slice::<JsonModel>(&file)?.elements)
+}
+
+#[test]
+fn test_block_shape() {
+    let cube = BlockShape::new(&BlockModel::Cube);
+    let slab = BlockShape::new(&BlockModel::Slab);
+    let stairs = BlockShape::new(&BlockModel::Stairs);
+    let air = BlockShape::new(&BlockModel::None);
+    assert_eq!(*cube.side(Direction::Left), FaceMask::FULL);
+    assert_eq!(*slab.side(Direction::Down), FaceMask::FULL);
+    assert_eq!(*slab.side(Direction::Up), FaceMask::EMPTY);
+    // the back of the stairs is covered by both boxes together
+    assert_eq!(*stairs.side(Direction::Back), FaceMask::FULL);
+    assert!(!stairs.side(Direction::Forward).covers(&FaceMask::FULL));
+
+    let full = &cube.boxes[0];
+    assert!(full.hidden_by(Direction::Left, &cube));
+    assert!(!full.hidden_by(Direction::Left, &air));
+    // a slab only covers the bottom half of the side of a cube
+    assert!(!full.hidden_by(Direction::Left, &slab));
+    let half = &slab.boxes[0];
+    assert!(half.hidden_by(Direction::Left, &cube));
+    assert!(half.hidden_by(Direction::Left, &slab));
+    // the top of a slab is in the middle of the block so it is always drawn
+    assert!(!half.hidden_by(Direction::Up, &cube));
+
+    assert_eq!(
+        slab.boxes[0].face(Direction::Up)[0],
+        [0.5, 0., 0.5],
+        "faces are shrunk to the box"
+    );
+    assert!(BlockShape::new(&BlockModel::Cross).cross);
+
+    // turned stairs have their full side where they are facing
+    let turned = stairs.rotated(Quat::from_rotation_y(std::f32::consts::PI));
+    assert_eq!(*turned.side(Direction::Forward), FaceMask::FULL);
+    assert_eq!(
+        turned.boxes[1],
+        ModelBox::new([0., 8., 8.], [16., 16., 16.])
+    );
+    assert_eq!(slab.rotated(Quat::IDENTITY), slab);
+
+    let path = std::env::temp_dir().join(format!("model_test_{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{"parent": "block/block", "elements": [{"from": [0, 0, 0], "to": [16, 15, 16], "faces": {}}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        load_json(&path).unwrap(),
+        [ModelBox::new([0., 0., 0.], [16., 15., 16.])]
+    );
+    std::fs::remove_file(&path).unwrap();
+    assert!(load_json(&path).is_err());
+}
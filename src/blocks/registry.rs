@@ -0,0 +1,400 @@
+use std::{path::Path, sync::OnceLock};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{BlockShape, BlockType, Direction, ModelBox, Orientation};
+
+/// where the block definitions are loaded from, relative to the working directory
+pub const BLOCKS_PATH: &str = "assets/blocks.ron";
+
+static REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+/// How the mesher builds a block, everything but [`BlockModel::Cross`] is collided with as it is drawn
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockModel {
+    /// nothing is drawn
+    None,
+    #[default]
+    Cube,
+    /// a cube that hides its faces against other blocks of the same liquid
+    Liquid,
+    /// two textured quads crossing diagonally like a plant, nothing collides with it
+    Cross,
+    /// the bottom half of a cube
+    Slab,
+    /// a slab with the top of the back half filled in
+    Stairs,
+    /// boxes in 16ths of a block like the elements of a minecraft model
+    Boxes(Vec<ModelBox>),
+    /// path in the assets folder to a minecraft style json model to take the boxes from
+    Json(String),
+}
+
+/// Something that can be different on each face of a block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BlockFaces<T> {
+    All(T),
+    TopBottomSides {
+        top: T,
+        bottom: T,
+        sides: T,
+    },
+    Faces {
+        up: T,
+        down: T,
+        left: T,
+        right: T,
+        forward: T,
+        back: T,
+    },
+}
+
+impl<T> BlockFaces<T> {
+    pub fn get(&self, direction: Direction) -> &T {
+        match self {
+            BlockFaces::All(all) => all,
+            BlockFaces::TopBottomSides { top, bottom, sides } => match direction {
+                Direction::Up => top,
+                Direction::Down => bottom,
+                _ => sides,
+            },
+            BlockFaces::Faces {
+                up,
+                down,
+                left,
+                right,
+                forward,
+                back,
+            } => match direction {
+                Direction::Up => up,
+                Direction::Down => down,
+                Direction::Left => left,
+                Direction::Right => right,
+                Direction::Forward => forward,
+                Direction::Back => back,
+            },
+        }
+    }
+}
+
+/// Everything about a kind of block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockProperties {
+    pub name: String,
+    /// things collide with its model and it can be targeted
+    pub solid: bool,
+    /// faces next to it are drawn
+    pub transparent: bool,
+    /// paths to the texture on each face
+    pub textures: Option<BlockFaces<String>>,
+    /// multiplied into the colour of each face
+    pub tint: Option<BlockFaces<[f32; 4]>>,
+    /// path to the texture shown in the inventory
+    pub icon: Option<String>,
+    /// how many seconds it takes to break in survival, none means it can't be broken
+    pub hardness: Option<f32>,
+    /// falls when there is nothing under it
+    pub gravity: bool,
+    /// how much light it gives off 0..=15
+    pub light: u8,
+    pub model: BlockModel,
+    /// how the model is turned when it is placed
+    pub orientation: Orientation,
+}
+
+impl Default for BlockProperties {
+    fn default() -> Self {
+        BlockProperties {
+            name: String::new(),
+            solid: true,
+            transparent: false,
+            textures: None,
+            tint: None,
+            icon: None,
+            hardness: Some(1.),
+            gravity: false,
+            light: 0,
+            model: BlockModel::Cube,
+            orientation: Orientation::None,
+        }
+    }
+}
+
+/// Every kind of block, a [`BlockType`] is an index into this
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockRegistry {
+    blocks: Vec<BlockProperties>,
+    /// the model of each block worked out from its properties
+    shapes: Vec<BlockShape>,
+}
+
+/// the names the constants on [`BlockType`] point at, in id order
+const BUILT_IN: [&str; 16] = [
+    "Air",
+    "Bedrock",
+    "Gravel",
+    "Dirt",
+    "Stone",
+    "Sand",
+    "GoldOre",
+    "IronOre",
+    "CoalOre",
+    "Grass",
+    "Water",
+    "DeadBush",
+    "TallGrass",
+    "StoneSlab",
+    "StoneStairs",
+    "DirtPath",
+];
+
+impl Default for BlockRegistry {
+    /// the [`BUILT_IN`] blocks so their ids line up with the constants on [`BlockType`]
+    /// these keep how they collide and are drawn, only their textures and tints come from [`BLOCKS_PATH`]
+    fn default() -> Self {
+        let blocks = BUILT_IN
+            .iter()
+            .map(|name| {
+                let block = BlockProperties {
+                    name: name.to_string(),
+                    ..Default::default()
+                };
+                match *name {
+                    "Air" => BlockProperties {
+                        solid: false,
+                        transparent: true,
+                        hardness: Some(0.),
+                        model: BlockModel::None,
+                        ..block
+                    },
+                    "Bedrock" => BlockProperties {
+                        hardness: None,
+                        ..block
+                    },
+                    "Gravel" => BlockProperties {
+                        hardness: Some(0.6),
+                        gravity: true,
+                        ..block
+                    },
+                    "Sand" => BlockProperties {
+                        hardness: Some(0.5),
+                        gravity: true,
+                        ..block
+                    },
+                    "Dirt" => BlockProperties {
+                        hardness: Some(0.5),
+                        ..block
+                    },
+                    "Stone" => BlockProperties {
+                        hardness: Some(1.5),
+                        ..block
+                    },
+                    "GoldOre" | "IronOre" | "CoalOre" => BlockProperties {
+                        hardness: Some(3.),
+                        ..block
+                    },
+                    "Grass" => BlockProperties {
+                        hardness: Some(0.6),
+                        ..block
+                    },
+                    "Water" => BlockProperties {
+                        solid: false,
+                        transparent: true,
+                        hardness: None,
+                        model: BlockModel::Liquid,
+                        ..block
+                    },
+                    "DeadBush" | "TallGrass" => BlockProperties {
+                        solid: false,
+                        transparent: true,
+                        hardness: Some(0.),
+                        model: BlockModel::Cross,
+                        ..block
+                    },
+                    "StoneSlab" => BlockProperties {
+                        hardness: Some(1.5),
+                        model: BlockModel::Slab,
+                        ..block
+                    },
+                    "StoneStairs" => BlockProperties {
+                        hardness: Some(1.5),
+                        model: BlockModel::Stairs,
+                        orientation: Orientation::Horizontal,
+                        ..block
+                    },
+                    "DirtPath" => BlockProperties {
+                        hardness: Some(0.5),
+                        model: BlockModel::Json("models/dirt_path.json".to_string()),
+                        ..block
+                    },
+                    _ => block,
+                }
+            })
+            .collect::<Vec<_>>();
+        let shapes = blocks
+            .iter()
+            .map(|block| BlockShape::new(&block.model))
+            .collect();
+        BlockRegistry { blocks, shapes }
+    }
+}
+
+impl BlockRegistry {
+    /// the registry every [`BlockType`] looks itself up in, loaded from [`BLOCKS_PATH`] the first time it is used
+    pub fn global() -> &'static BlockRegistry {
+        REGISTRY.get_or_init(|| BlockRegistry::load(BLOCKS_PATH))
+    }
+
+    /// loads the definitions in path on top of the defaults
+    /// a definition with the same name as a default replaces it, anything else is added to the end
+    pub fn load(path: impl AsRef<Path>) -> BlockRegistry {
+        let path = path.as_ref();
+        let mut registry = BlockRegistry::default();
+        let file = match std::fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!(
+                    "Failed to read {}, blocks will have no textures: {}",
+                    path.display(),
+                    e
+                );
+                return registry;
+            }
+        };
+        match ron::from_str::<Vec<BlockProperties>>(&file) {
+            Ok(blocks) => {
+                for block in blocks {
+                    registry.register(block);
+                }
+            }
+            Err(e) => error!("Failed to parse {}: {}", path.display(), e),
+        }
+        registry
+    }
+
+    /// adds a block or replaces the one with the same name
+    pub fn register(&mut self, block: BlockProperties) -> Option<BlockType> {
+        let shape = BlockShape::new(&block.model);
+        if let Some(existing) = self.by_name(&block.name) {
+            self.blocks[existing.id() as usize] = block;
+            self.shapes[existing.id() as usize] = shape;
+            return Some(existing);
+        }
+        let Ok(id) = u16::try_from(self.blocks.len()) else {
+            error!("Too many blocks, can't add {}", block.name);
+            return None;
+        };
+        self.blocks.push(block);
+        self.shapes.push(shape);
+        Some(BlockType::from_id(id))
+    }
+
+    /// unknown blocks are treated as air
+    pub fn get(&self, block: BlockType) -> &BlockProperties {
+        self.blocks
+            .get(block.id() as usize)
+            .unwrap_or(&self.blocks[0])
+    }
+
+    /// unknown blocks are treated as air
+    pub fn shape(&self, block: BlockType) -> &BlockShape {
+        self.shapes
+            .get(block.id() as usize)
+            .unwrap_or(&self.shapes[0])
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<BlockType> {
+        self.blocks
+            .iter()
+            .position(|block| block.name == name)
+            .map(|id| BlockType::from_id(id as u16))
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BlockType, &BlockProperties)> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .map(|(id, block)| (BlockType::from_id(id as u16), block))
+    }
+}
+
+#[test]
+fn test_registry_defaults() {
+    let mut registry = BlockRegistry::default();
+    assert_eq!(registry.len(), BUILT_IN.len());
+    assert!(!registry.get(BlockType::Air).solid);
+    let next = registry.len() as u16;
+    let stone = registry.register(BlockProperties {
+        name: "Stone".to_string(),
+        hardness: Some(2.),
+        ..Default::default()
+    });
+    assert_eq!(stone, Some(BlockType::Stone));
+    assert_eq!(registry.get(BlockType::Stone).hardness, Some(2.));
+    let glow = registry.register(BlockProperties {
+        name: "Glowstone".to_string(),
+        light: 15,
+        ..Default::default()
+    });
+    assert_eq!(glow, Some(BlockType::from_id(next)));
+    assert_eq!(registry.get(BlockType::from_id(500)).name, "Air");
+    // without the file water and plants still aren't solid cubes
+    assert_eq!(registry.get(BlockType::Water).model, BlockModel::Liquid);
+    assert!(!registry.get(BlockType::Water).solid);
+    assert!(!registry.get(BlockType::TallGrass).solid);
+    assert!(registry.get(BlockType::Sand).gravity);
+}
+
+#[test]
+fn test_blocks_asset() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(BLOCKS_PATH);
+    let file = std::fs::read_to_string(&path).unwrap();
+    let blocks: Vec<BlockProperties> = ron::from_str(&file).unwrap();
+    let registry = BlockRegistry::load(&path);
+    // every block in the file lands on its constant and nothing is left as a bare cube
+    assert_eq!(registry.len(), blocks.len().max(BUILT_IN.len()));
+    for (id, name) in BUILT_IN.iter().enumerate() {
+        assert_eq!(registry.by_name(name), Some(BlockType::from_id(id as u16)));
+    }
+    for (block, properties) in registry.iter().skip(1) {
+        assert!(properties.textures.is_some(), "{:?} has no textures", block);
+        // plants are walked through
+        if properties.model == BlockModel::Cross {
+            assert!(!properties.solid, "{:?} is a solid plant", block);
+        }
+    }
+    assert_eq!(registry.shape(BlockType::StoneSlab).boxes.len(), 1);
+    assert_eq!(registry.shape(BlockType::StoneStairs).boxes.len(), 2);
+    assert!(registry.get(BlockType::Sand).gravity);
+    assert_eq!(registry.get(BlockType::Bedrock).hardness, None);
+}
+
+#[test]
+fn test_block_faces() {
+    let faces = BlockFaces::TopBottomSides {
+        top: 0,
+        bottom: 1,
+        sides: 2,
+    };
+    assert_eq!(*faces.get(Direction::Up), 0);
+    assert_eq!(*faces.get(Direction::Down), 1);
+    assert_eq!(*faces.get(Direction::Left), 2);
+    assert_eq!(*faces.get(Direction::Back), 2);
+    assert_eq!(BlockType::Grass.tint(Direction::Up), [0.2, 0.8, 0.2, 1.]);
+    assert_eq!(BlockType::Grass.tint(Direction::Forward), [1.; 4]);
+    assert_eq!(
+        BlockType::Grass.texture(Direction::Down),
+        Some("PureBDcraft/textures/block/dirt.png")
+    );
+    assert_eq!(BlockType::Air.texture(Direction::Up), None);
+}
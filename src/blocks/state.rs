@@ -0,0 +1,214 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{BlockModel, BlockType, Direction};
+
+/// What a block takes from the way it was placed
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    /// always drawn the same way
+    #[default]
+    None,
+    /// turned to face the way the player was looking but kept upright like stairs
+    Horizontal,
+    /// turned to face the way the player was looking
+    Facing,
+    /// lined up with the face it was placed against like a log
+    Axis,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    #[default]
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn of(direction: Direction) -> Axis {
+        match direction {
+            Direction::Left | Direction::Right => Axis::X,
+            Direction::Up | Direction::Down => Axis::Y,
+            Direction::Forward | Direction::Back => Axis::Z,
+        }
+    }
+}
+
+/// facings in the order they are stored so a state of zero is the way models are drawn, with their back to [`Direction::Back`]
+const FACINGS: [Direction; 6] = [
+    Direction::Back,
+    Direction::Forward,
+    Direction::Left,
+    Direction::Right,
+    Direction::Up,
+    Direction::Down,
+];
+
+/// Bits kept next to every block in a chunk, what they mean depends on the block
+///
+/// facing is 3 bits, axis 2 bits, waterlogged 1 bit and the fluid level 3 bits
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockState(u16);
+
+impl BlockState {
+    pub const DEFAULT: BlockState = BlockState(0);
+
+    const FACING: u16 = 0b111;
+    const AXIS_SHIFT: u16 = 3;
+    const WATERLOGGED: u16 = 1 << 5;
+    const LEVEL_SHIFT: u16 = 6;
+    const LEVEL: u16 = 0b111;
+    pub const MAX_FLUID_LEVEL: u8 = Self::LEVEL as u8;
+
+    /// the state a block gets when a player looking along look places it against face of another block
+    /// blocks without a full cube placed into water are waterlogged
+    pub fn placed(
+        block: BlockType,
+        look: Vec3,
+        face: Direction,
+        replacing: BlockType,
+    ) -> BlockState {
+        let properties = block.properties();
+        let mut state = match properties.orientation {
+            Orientation::None => BlockState::DEFAULT,
+            Orientation::Horizontal => {
+                // looking straight up or down has no horizontal direction to face
+                let flat = look.with_y(0.);
+                let facing = if flat.length_squared() > 1e-6 {
+                    Direction::from_vec(flat)
+                } else {
+                    Direction::Forward
+                };
+                BlockState::DEFAULT.with_facing(facing)
+            }
+            Orientation::Facing => BlockState::DEFAULT.with_facing(Direction::from_vec(look)),
+            Orientation::Axis => BlockState::DEFAULT.with_axis(Axis::of(face)),
+        };
+        let full = matches!(
+            properties.model,
+            BlockModel::Cube | BlockModel::Liquid | BlockModel::None
+        );
+        if replacing == BlockType::Water && !full {
+            state = state.with_waterlogged(true);
+        }
+        state
+    }
+
+    pub fn facing(&self) -> Direction {
+        FACINGS[((self.0 & Self::FACING) as usize).min(5)]
+    }
+
+    pub fn with_facing(self, facing: Direction) -> BlockState {
+        let index = FACINGS.iter().position(|f| *f == facing).unwrap_or(0) as u16;
+        BlockState((self.0 & !Self::FACING) | index)
+    }
+
+    pub fn axis(&self) -> Axis {
+        match (self.0 >> Self::AXIS_SHIFT) & 0b11 {
+            1 => Axis::X,
+            2 => Axis::Z,
+            _ => Axis::Y,
+        }
+    }
+
+    pub fn with_axis(self, axis: Axis) -> BlockState {
+        let bits = match axis {
+            Axis::Y => 0,
+            Axis::X => 1,
+            Axis::Z => 2,
+        };
+        BlockState((self.0 & !(0b11 << Self::AXIS_SHIFT)) | (bits << Self::AXIS_SHIFT))
+    }
+
+    /// there is water in the block as well
+    pub fn waterlogged(&self) -> bool {
+        self.0 & Self::WATERLOGGED != 0
+    }
+
+    pub fn with_waterlogged(self, waterlogged: bool) -> BlockState {
+        if waterlogged {
+            BlockState(self.0 | Self::WATERLOGGED)
+        } else {
+            BlockState(self.0 & !Self::WATERLOGGED)
+        }
+    }
+
+    /// how far a liquid has drained 0..=[`BlockState::MAX_FLUID_LEVEL`], 0 is full
+    pub fn fluid_level(&self) -> u8 {
+        ((self.0 >> Self::LEVEL_SHIFT) & Self::LEVEL) as u8
+    }
+
+    /// levels past [`BlockState::MAX_FLUID_LEVEL`] are clamped to it
+    pub fn with_fluid_level(self, level: u8) -> BlockState {
+        let level = level.min(Self::MAX_FLUID_LEVEL) as u16;
+        BlockState((self.0 & !(Self::LEVEL << Self::LEVEL_SHIFT)) | (level << Self::LEVEL_SHIFT))
+    }
+
+    /// turns the model from the way it is drawn to the way the block is facing and lined up
+    pub fn rotation(&self) -> Quat {
+        let facing = match self.facing() {
+            Direction::Back => Quat::IDENTITY,
+            Direction::Forward => Quat::from_rotation_y(PI),
+            Direction::Left => Quat::from_rotation_y(FRAC_PI_2),
+            Direction::Right => Quat::from_rotation_y(-FRAC_PI_2),
+            Direction::Up => Quat::from_rotation_x(FRAC_PI_2),
+            Direction::Down => Quat::from_rotation_x(-FRAC_PI_2),
+        };
+        let axis = match self.axis() {
+            Axis::X => Quat::from_rotation_z(-FRAC_PI_2),
+            Axis::Y => Quat::IDENTITY,
+            Axis::Z => Quat::from_rotation_x(FRAC_PI_2),
+        };
+        axis * facing
+    }
+}
+
+#[test]
+fn test_block_state() {
+    let state = BlockState::DEFAULT
+        .with_facing(Direction::Left)
+        .with_axis(Axis::Z)
+        .with_waterlogged(true)
+        .with_fluid_level(5);
+    assert_eq!(state.facing(), Direction::Left);
+    assert_eq!(state.axis(), Axis::Z);
+    assert!(state.waterlogged());
+    assert_eq!(state.fluid_level(), 5);
+    let state = state.with_facing(Direction::Up).with_waterlogged(false);
+    assert_eq!(state.facing(), Direction::Up);
+    assert_eq!(state.axis(), Axis::Z);
+    assert!(!state.waterlogged());
+    assert_eq!(state.fluid_level(), 5);
+    let drained = state.with_fluid_level(20);
+    assert_eq!(drained.fluid_level(), BlockState::MAX_FLUID_LEVEL);
+    assert_eq!(drained.with_fluid_level(0).fluid_level(), 0);
+    assert_eq!(drained.facing(), Direction::Up);
+
+    // the default state leaves models as they are
+    assert_eq!(BlockState::DEFAULT.facing(), Direction::Back);
+    assert_eq!(BlockState::DEFAULT.rotation(), Quat::IDENTITY);
+    for facing in FACINGS {
+        let rotation = BlockState::DEFAULT.with_facing(facing).rotation();
+        assert_eq!(Direction::Back.rotated(rotation), facing);
+    }
+    let log = BlockState::DEFAULT.with_axis(Axis::X).rotation();
+    assert_eq!(Direction::Up.rotated(log), Direction::Right);
+
+    // stairs face the way the player looks whatever the pitch
+    let look = Vec3::new(0.3, -0.9, 0.5);
+    let stairs = BlockState::placed(BlockType::StoneStairs, look, Direction::Up, BlockType::Air);
+    assert_eq!(stairs.facing(), Direction::Forward);
+    assert!(!stairs.waterlogged());
+    for look in [Vec3::Y, Vec3::NEG_Y] {
+        let stairs =
+            BlockState::placed(BlockType::StoneStairs, look, Direction::Up, BlockType::Air);
+        assert_eq!(stairs.facing(), Direction::Forward);
+    }
+    let slab = BlockState::placed(BlockType::StoneSlab, look, Direction::Up, BlockType::Water);
+    assert!(slab.waterlogged());
+    let stone = BlockState::placed(BlockType::Stone, look, Direction::Up, BlockType::Water);
+    assert_eq!(stone, BlockState::DEFAULT);
+}
@@ -3,14 +3,25 @@ use bevy::ecs::event::Events;
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, PrimaryWindow};
+use serde::{Deserialize, Serialize};
 
-use crate::GameState;
+use crate::{
+    actions::{Action, Actions},
+    GameState,
+};
 
-/// Mouse sensitivity and movement speed
-#[derive(Resource)]
+/// Mouse sensitivity, gamepad stick response and movement speed
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MovementSettings {
     pub sensitivity: f32,
     pub speed: f32,
+    /// how far a stick has to be pushed before it does anything, 0..1
+    pub stick_deadzone: f32,
+    /// response curve of the sticks, 1 is linear and higher gives finer control near the middle
+    pub stick_curve: f32,
+    /// how fast the right stick turns the camera in degrees per second
+    pub stick_look_speed: f32,
 }
 
 impl Default for MovementSettings {
@@ -18,32 +29,53 @@ impl Default for MovementSettings {
         Self {
             sensitivity: 0.00012,
             speed: 12.,
+            stick_deadzone: 0.15,
+            stick_curve: 2.,
+            stick_look_speed: 180.,
         }
     }
 }
 
-/// Key configuration
-#[derive(Resource)]
-pub struct KeyBindings {
-    pub move_forward: KeyCode,
-    pub move_backward: KeyCode,
-    pub move_left: KeyCode,
-    pub move_right: KeyCode,
-    pub move_ascend: KeyCode,
-    pub move_descend: KeyCode,
-    pub toggle_grab_cursor: KeyCode,
-}
+impl MovementSettings {
+    /// applies the deadzone and curve to a stick
+    /// the result is rescaled so it still reaches 1 at the edge of the deadzone
+    pub fn shape_stick(&self, stick: Vec2) -> Vec2 {
+        let length = stick.length().min(1.);
+        if length <= self.stick_deadzone {
+            return Vec2::ZERO;
+        }
+        let scaled = (length - self.stick_deadzone) / (1. - self.stick_deadzone);
+        stick.normalize() * scaled.powf(self.stick_curve)
+    }
 
-impl Default for KeyBindings {
-    fn default() -> Self {
-        Self {
-            move_forward: KeyCode::KeyW,
-            move_backward: KeyCode::KeyS,
-            move_left: KeyCode::KeyA,
-            move_right: KeyCode::KeyD,
-            move_ascend: KeyCode::Space,
-            move_descend: KeyCode::ShiftLeft,
-            toggle_grab_cursor: KeyCode::Escape,
+    /// pulls every value back into a usable range, warning about anything that was changed
+    pub fn validate(&mut self) {
+        let defaults = MovementSettings::default();
+        if self.sensitivity.is_nan() || self.sensitivity <= 0. {
+            warn!("sensitivity must be positive, got {}", self.sensitivity);
+            self.sensitivity = defaults.sensitivity;
+        }
+        if self.speed.is_nan() || self.speed <= 0. {
+            warn!("speed must be positive, got {}", self.speed);
+            self.speed = defaults.speed;
+        }
+        if !(0. ..1.).contains(&self.stick_deadzone) {
+            warn!(
+                "stick_deadzone must be in 0..1, got {}",
+                self.stick_deadzone
+            );
+            self.stick_deadzone = defaults.stick_deadzone;
+        }
+        if self.stick_curve.is_nan() || self.stick_curve <= 0. {
+            warn!("stick_curve must be positive, got {}", self.stick_curve);
+            self.stick_curve = defaults.stick_curve;
+        }
+        if self.stick_look_speed.is_nan() || self.stick_look_speed < 0. {
+            warn!(
+                "stick_look_speed can't be negative, got {}",
+                self.stick_look_speed
+            );
+            self.stick_look_speed = defaults.stick_look_speed;
         }
     }
 }
@@ -76,13 +108,9 @@ fn initial_grab_cursor(mut primary_window: Query<&mut Window, With<PrimaryWindow
     }
 }
 
-fn cursor_grab(
-    keys: Res<ButtonInput<KeyCode>>,
-    key_bindings: Res<KeyBindings>,
-    mut primary_window: Query<&mut Window, With<PrimaryWindow>>,
-) {
+fn cursor_grab(actions: Actions, mut primary_window: Query<&mut Window, With<PrimaryWindow>>) {
     if let Ok(mut window) = primary_window.get_single_mut() {
-        if keys.just_pressed(key_bindings.toggle_grab_cursor) {
+        if actions.just_pressed(Action::ToggleGrabCursor) {
             toggle_grab_cursor(&mut window);
         }
     } else {
@@ -96,9 +124,14 @@ impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         println!("run");
         app.init_resource::<MovementSettings>()
-            .init_resource::<KeyBindings>()
             // .add_systems(Startup, setup_player)
-            .add_systems(Startup, initial_grab_cursor)
-            .add_systems(Update, cursor_grab);
+            .add_systems(
+                Startup,
+                initial_grab_cursor.run_if(any_with_component::<PrimaryWindow>),
+            )
+            .add_systems(
+                Update,
+                cursor_grab.run_if(any_with_component::<PrimaryWindow>),
+            );
     }
 }
@@ -0,0 +1,167 @@
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+/// A voxel sandbox
+#[derive(Parser, Debug, Default)]
+#[command(version, about)]
+pub struct Cli {
+    /// the world seed, anything that isn't a number gets hashed
+    #[arg(long)]
+    pub seed: Option<String>,
+    /// how many chunks to load around the player, overrides settings.ron for this run
+    #[arg(long)]
+    pub view_distance: Option<i32>,
+    /// directory to keep the world in, the seed is saved here so the world comes back the same
+    #[arg(long)]
+    pub world: Option<PathBuf>,
+    /// run the simulation without a window or a gpu
+    #[arg(long)]
+    pub headless: bool,
+    /// exit after this many frames
+    #[arg(long)]
+    pub frames: Option<u32>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// generate part of the world and write it to files instead of playing
+    Export(ExportArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// chunk x to center the region on
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    pub x: i32,
+    /// chunk z to center the region on
+    #[arg(long, default_value_t = 0, allow_negative_numbers = true)]
+    pub z: i32,
+    /// how many chunk columns to generate on each side of the center
+    #[arg(long, default_value_t = 4)]
+    pub radius: i32,
+    /// directory to write map.png, histogram.csv and terrain.obj to
+    #[arg(long, default_value = "export")]
+    pub out: PathBuf,
+    /// also write the chunk meshes as an obj
+    #[arg(long)]
+    pub obj: bool,
+}
+
+/// turns a seed from the user into a number
+/// numbers are used as they are everything else is hashed
+pub fn parse_seed(seed: &str) -> u64 {
+    match seed.parse() {
+        Ok(seed) => seed,
+        Err(_) => {
+            let mut hasher = bevy::utils::FixedState.build_hasher();
+            seed.hash(&mut hasher);
+            hasher.finish()
+        }
+    }
+}
+
+/// What is saved in the world directory
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorldInfo {
+    pub seed: u64,
+}
+
+impl WorldInfo {
+    const FILE: &'static str = "world.ron";
+
+    pub fn load(dir: &Path) -> Option<WorldInfo> {
+        let path = dir.join(Self::FILE);
+        let file = match std::fs::read_to_string(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    error!("Failed to read {}: {}", path.display(), e);
+                }
+                return None;
+            }
+        };
+        match ron::from_str(&file) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                error!("Failed to parse {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, dir: &Path) {
+        let path = dir.join(Self::FILE);
+        let file = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to serialize world info: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, file)) {
+            error!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+impl Cli {
+    /// works out which seed to use
+    /// an existing world keeps its seed, a new world saves the seed it was made with
+    pub fn seed(&self) -> Option<u64> {
+        let seed = self.seed.as_deref().map(parse_seed);
+        let Some(dir) = &self.world else {
+            return seed;
+        };
+        if let Some(info) = WorldInfo::load(dir) {
+            if seed.is_some_and(|seed| seed != info.seed) {
+                warn!(
+                    "{} already has seed {}, ignoring --seed",
+                    dir.display(),
+                    info.seed
+                );
+            }
+            return Some(info.seed);
+        }
+        let seed = seed.unwrap_or_else(|| {
+            use rand::Rng;
+            rand::thread_rng().gen()
+        });
+        WorldInfo { seed }.save(dir);
+        Some(seed)
+    }
+}
+
+/// stops the app once [`Cli::frames`] frames have run
+pub fn exit_after(frames: u32) -> impl FnMut(Res<bevy::core::FrameCount>, EventWriter<AppExit>) {
+    move |count, mut exit| {
+        if count.0 >= frames {
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+#[test]
+fn test_parse_seed() {
+    assert_eq!(parse_seed("42"), 42);
+    assert_eq!(parse_seed("hello"), parse_seed("hello"));
+    assert_ne!(parse_seed("hello"), parse_seed("world"));
+    assert_ne!(parse_seed("-1"), parse_seed("1"));
+    let cli = Cli::try_parse_from(["game", "--seed", "7", "--headless", "--frames", "10"]).unwrap();
+    assert!(cli.headless);
+    assert_eq!(cli.frames, Some(10));
+    assert_eq!(cli.seed(), Some(7));
+    let cli = Cli::try_parse_from(["game", "export", "--x", "-2", "--obj"]).unwrap();
+    let Some(Command::Export(args)) = cli.command else {
+        panic!("expected export");
+    };
+    assert_eq!((args.x, args.z, args.radius), (-2, 0, 4));
+    assert!(args.obj);
+}
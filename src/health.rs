@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+use crate::{
+    physics::{Frozen, PhysicsOutput, Velocity},
+    player_controller::{GameMode, Player, PlayerCamera, Spawning},
+    prelude::*,
+    terrain::Map,
+};
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlayerDied>().add_systems(
+            Update,
+            (
+                (fall_damage, drowning).after(crate::physics::apply_velocity),
+                kill_players,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// how many blocks something can fall before it takes damage
+const SAFE_FALL_HEIGHT: f32 = 3.;
+/// how long the player can hold there breath in seconds
+const MAX_BREATH: f32 = 10.;
+
+#[derive(Component)]
+pub struct Health {
+    current: f32,
+    max: f32,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Health {
+            current: 20.,
+            max: 20.,
+        }
+    }
+}
+
+impl Health {
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn damage(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.max;
+    }
+}
+
+/// how long the player has left before they start to drown
+#[derive(Component)]
+pub struct Breath(f32);
+
+impl Default for Breath {
+    fn default() -> Self {
+        Breath(MAX_BREATH)
+    }
+}
+
+impl Breath {
+    /// uses up breath under water returning the damage taken, one every second once it runs out
+    fn hold(&mut self, delta: f32) -> f32 {
+        self.0 -= delta;
+        let mut damage = 0.;
+        while self.0 <= 0. {
+            damage += 1.;
+            self.0 += 1.;
+        }
+        damage
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerDied(pub Entity);
+
+/// damages players when they hit the ground faster than falling [`SAFE_FALL_HEIGHT`] blocks would
+fn fall_damage(
+    mut players: Query<(&PhysicsOutput, &GameMode, &mut Health), (With<Player>, Without<Frozen>)>,
+) {
+    for (output, mode, mut health) in &mut players {
+        if !mode.takes_damage() {
+            continue;
+        }
+        let Some(speed) = output.just_landed() else {
+            continue;
+        };
+        health.damage(fall_damage_for(speed));
+    }
+}
+
+/// the damage for hitting the ground at speed, one for each whole block fallen past [`SAFE_FALL_HEIGHT`]
+fn fall_damage_for(speed: f32) -> f32 {
+    // v^2 = 2gh
+    let fallen = speed * speed / (2. * 9.8);
+    (fallen - SAFE_FALL_HEIGHT).max(0.).floor()
+}
+
+fn drowning(
+    mut players: Query<
+        (&PlayerCamera, &GameMode, &mut Breath, &mut Health),
+        (With<Player>, Without<Frozen>),
+    >,
+    cameras: Query<&GlobalTransform>,
+    map: Res<Map>,
+    time: Res<Time>,
+) {
+    for (camera, mode, mut breath, mut health) in &mut players {
+        if !mode.takes_damage() {
+            breath.0 = MAX_BREATH;
+            continue;
+        }
+        let Ok(head) = cameras.get(camera.get()) else {
+            error!("Player has no camera;");
+            continue;
+        };
+        let head = BlockId::from_translation(head.translation());
+        if map.get_block(head) != BlockType::Water {
+            breath.0 = MAX_BREATH;
+            continue;
+        }
+        let damage = breath.hold(time.delta_secs());
+        health.damage(damage);
+    }
+}
+
+/// resets dead players and sends them back to spawn
+fn kill_players(
+    mut commands: Commands,
+    mut players: Query<
+        (Entity, &mut Health, &mut Breath, &mut Velocity),
+        (With<Player>, Without<Frozen>),
+    >,
+    mut died: EventWriter<PlayerDied>,
+) {
+    for (player, mut health, mut breath, mut velocity) in &mut players {
+        if !health.is_dead() {
+            continue;
+        }
+        info!("Player died");
+        died.send(PlayerDied(player));
+        health.reset();
+        *breath = Breath::default();
+        **velocity = Vec3::ZERO;
+        commands.entity(player).insert((Spawning, Frozen));
+    }
+}
+
+#[test]
+fn test_fall_damage() {
+    let speed_after = |height: f32| (2. * 9.8 * height).sqrt();
+    assert_eq!(fall_damage_for(0.), 0.);
+    assert_eq!(fall_damage_for(speed_after(SAFE_FALL_HEIGHT)), 0.);
+    assert_eq!(fall_damage_for(speed_after(SAFE_FALL_HEIGHT + 0.5)), 0.);
+    assert_eq!(fall_damage_for(speed_after(SAFE_FALL_HEIGHT + 4.5)), 4.);
+    // falling far enough kills
+    let mut health = Health::default();
+    health.damage(fall_damage_for(speed_after(30.)));
+    assert!(health.is_dead());
+    assert_eq!(health.current(), 0.);
+}
+
+#[test]
+fn test_drowning() {
+    let mut breath = Breath::default();
+    assert_eq!(breath.hold(MAX_BREATH - 0.5), 0.);
+    assert_eq!(breath.hold(1.), 1.);
+    assert_eq!(breath.hold(0.25), 0.);
+    // a long frame still takes one damage for every second
+    assert_eq!(breath.hold(3.), 3.);
+}
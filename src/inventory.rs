@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Action, Actions},
+    player_controller::Player,
+    prelude::*,
+    Playing,
+};
+
+pub struct InventoryPlugin;
+
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, select_hotbar_slot.in_set(Playing));
+    }
+}
+
+pub const HOTBAR_SIZE: usize = 9;
+pub const STORAGE_SIZE: usize = 27;
+pub const MAX_STACK: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemStack {
+    pub block: BlockType,
+    pub count: u32,
+}
+
+/// The blocks a player is carrying
+/// the first [`HOTBAR_SIZE`] slots are the hotbar the rest are storage
+#[derive(Component, Debug)]
+pub struct Inventory {
+    slots: [Option<ItemStack>; HOTBAR_SIZE + STORAGE_SIZE],
+    selected: usize,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory {
+            slots: [None; HOTBAR_SIZE + STORAGE_SIZE],
+            selected: 0,
+        }
+    }
+}
+
+impl Inventory {
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..HOTBAR_SIZE]
+    }
+
+    pub fn storage(&self) -> &[Option<ItemStack>] {
+        &self.slots[HOTBAR_SIZE..]
+    }
+
+    /// the hotbar slot the player is holding
+    pub fn selected_slot(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        if slot < HOTBAR_SIZE {
+            self.selected = slot;
+        }
+    }
+
+    /// moves the selected slot by `by` wrapping around the ends of the hotbar
+    pub fn scroll(&mut self, by: i32) {
+        self.selected = (self.selected as i32 + by).rem_euclid(HOTBAR_SIZE as i32) as usize;
+    }
+
+    pub fn selected(&self) -> Option<ItemStack> {
+        self.slots[self.selected]
+    }
+
+    pub fn set_selected(&mut self, stack: Option<ItemStack>) {
+        self.slots[self.selected] = stack;
+    }
+
+    /// adds blocks to the inventory topping up existing stacks before using empty slots
+    /// returns how many did not fit
+    pub fn add(&mut self, block: BlockType, mut count: u32) -> u32 {
+        if block == BlockType::Air {
+            return 0;
+        }
+        for stack in self.slots.iter_mut().flatten() {
+            if stack.block != block {
+                continue;
+            }
+            let moved = count.min(MAX_STACK - stack.count);
+            stack.count += moved;
+            count -= moved;
+            if count == 0 {
+                return 0;
+            }
+        }
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            let moved = count.min(MAX_STACK);
+            *slot = Some(ItemStack {
+                block,
+                count: moved,
+            });
+            count -= moved;
+            if count == 0 {
+                return 0;
+            }
+        }
+        count
+    }
+
+    /// removes one block from the selected slot returning it
+    pub fn take_selected(&mut self) -> Option<BlockType> {
+        let slot = &mut self.slots[self.selected];
+        let stack = slot.as_mut()?;
+        let block = stack.block;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(block)
+    }
+}
+
+/// scrolling moves one slot per notch, fractions are kept until they add up to a whole notch
+fn select_hotbar_slot(
+    actions: Actions,
+    mut players: Query<&mut Inventory, With<Player>>,
+    mut scrolled: Local<f32>,
+) {
+    let mut scroll = scroll_slots(&mut scrolled, actions.scroll());
+    if actions.just_pressed(Action::HotbarNext) {
+        scroll += 1;
+    }
+    if actions.just_pressed(Action::HotbarPrevious) {
+        scroll -= 1;
+    }
+    for mut inventory in &mut players {
+        for (slot, action) in Action::HOTBAR.into_iter().enumerate() {
+            if actions.just_pressed(action) {
+                inventory.select(slot);
+            }
+        }
+        if scroll != 0 {
+            inventory.scroll(scroll);
+        }
+    }
+}
+
+/// adds notches to what has been scrolled returning the whole slots to move
+fn scroll_slots(scrolled: &mut f32, notches: f32) -> i32 {
+    *scrolled += notches;
+    let slots = scrolled.trunc();
+    *scrolled -= slots;
+    slots as i32
+}
+
+#[test]
+fn test_inventory_stacks() {
+    let mut inventory = Inventory::default();
+    assert_eq!(inventory.add(BlockType::Dirt, MAX_STACK + 10), 0);
+    assert_eq!(inventory.add(BlockType::Stone, 1), 0);
+    assert_eq!(
+        inventory.hotbar()[..3],
+        [
+            Some(ItemStack {
+                block: BlockType::Dirt,
+                count: MAX_STACK
+            }),
+            Some(ItemStack {
+                block: BlockType::Dirt,
+                count: 10
+            }),
+            Some(ItemStack {
+                block: BlockType::Stone,
+                count: 1
+            }),
+        ]
+    );
+    // tops up the existing stack before using a new slot
+    inventory.add(BlockType::Dirt, 5);
+    assert_eq!(inventory.hotbar()[1].map(|stack| stack.count), Some(15));
+    assert_eq!(inventory.hotbar()[3], None);
+
+    let mut full = Inventory::default();
+    let space = (HOTBAR_SIZE + STORAGE_SIZE) as u32 * MAX_STACK;
+    assert_eq!(full.add(BlockType::Sand, space + 3), 3);
+    assert_eq!(inventory.add(BlockType::Air, 3), 0);
+}
+
+#[test]
+fn test_inventory_take_selected() {
+    let mut inventory = Inventory::default();
+    inventory.add(BlockType::Sand, 2);
+    inventory.scroll(-1);
+    assert_eq!(inventory.selected_slot(), HOTBAR_SIZE - 1);
+    assert_eq!(inventory.take_selected(), None);
+    inventory.scroll(1);
+    assert_eq!(inventory.take_selected(), Some(BlockType::Sand));
+    assert_eq!(inventory.take_selected(), Some(BlockType::Sand));
+    assert_eq!(inventory.take_selected(), None);
+    assert_eq!(inventory.selected(), None);
+}
+
+#[test]
+fn test_scroll_slots() {
+    let mut scrolled = 0.;
+    assert_eq!(scroll_slots(&mut scrolled, 1.), 1);
+    assert_eq!(scroll_slots(&mut scrolled, -2.), -2);
+    // touchpads scroll a bit at a time
+    assert_eq!(scroll_slots(&mut scrolled, 0.25), 0);
+    assert_eq!(scroll_slots(&mut scrolled, 0.5), 0);
+    assert_eq!(scroll_slots(&mut scrolled, 0.25), 1);
+    assert_eq!(scroll_slots(&mut scrolled, -0.75), 0);
+    assert_eq!(scroll_slots(&mut scrolled, -0.5), -1);
+    assert_eq!(scrolled, -0.25);
+}
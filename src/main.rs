@@ -3,7 +3,6 @@ use bevy::{prelude::*, utils::HashMap};
 use avian3d::prelude::*;
 use blocks::BlockType;
 use prelude::ChunkId;
-use settings::ViewDistance;
 use textures::TextureHandles;
 
 use crate::prelude::CHUNK_SIZE;
@@ -27,13 +26,53 @@ mod components;
 
 mod physics;
 
+mod health;
+
+mod inventory;
+
+mod actions;
+
+mod cli;
+
 fn main() {
+    let cli = <cli::Cli as clap::Parser>::parse();
+    if let Some(cli::Command::Export(args)) = &cli.command {
+        let seed = cli.seed().unwrap_or(*terrain::Seed::default());
+        if let Err(e) = terrain::export::export(seed, args) {
+            eprintln!("Export failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let mut app = App::new();
-    app.add_plugins((
-        DefaultPlugins,
-        PhysicsPlugins::default(),
-        physics::PhysicsPlugin,
-    ));
+    if cli.headless {
+        app.add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: bevy::window::ExitCondition::DontExit,
+                    ..Default::default()
+                })
+                .set(bevy::render::RenderPlugin {
+                    render_creation: bevy::render::settings::WgpuSettings {
+                        backends: None,
+                        ..Default::default()
+                    }
+                    .into(),
+                    ..Default::default()
+                })
+                .disable::<bevy::winit::WinitPlugin>(),
+        )
+        .add_plugins(bevy::app::ScheduleRunnerPlugin::run_loop(
+            std::time::Duration::from_secs_f64(1. / 60.),
+        ));
+    } else {
+        app.add_plugins(DefaultPlugins);
+    }
+    app.add_plugins((PhysicsPlugins::default(), physics::PhysicsPlugin));
+    if let Some(frames) = cli.frames {
+        app.add_systems(Last, cli::exit_after(frames));
+    }
     #[cfg(debug_assertions)]
     app.add_systems(Update, frame_time);
     #[cfg(debug_assertions)]
@@ -43,20 +82,35 @@ fn main() {
         bevy::diagnostic::EntityCountDiagnosticsPlugin,
         // bevy_diagnostics_explorer::DiagnosticExplorerAgentPlugin,
     ));
-    app.add_plugins((textures::TexturePlugin, cam::PlayerPlugin));
+    app.add_plugins((
+        blocks::BlocksPlugin,
+        actions::ActionsPlugin,
+        settings::SettingsPlugin::default(),
+        textures::TexturePlugin,
+        cam::PlayerPlugin,
+    ));
     app.add_systems(Startup, spawn_cube);
     app.add_systems(PreUpdate, components::name_chunks);
     // app.insert_resource(world::Map::new_with_seed(3))
     //     .init_resource::<ChunkMeshTasks>();
+    if let Some(seed) = cli.seed() {
+        app.insert_resource(terrain::Seed::new(seed));
+    }
+    if let Some(view_distance) = cli.view_distance {
+        let mut view_distance = settings::ViewDistance(view_distance);
+        view_distance.validate();
+        app.insert_resource(view_distance);
+    }
     app.add_plugins(terrain::TerrainPlugin);
-    app.init_resource::<ViewDistance>();
-    app.register_type::<ViewDistance>();
     // app.add_plugins(belly::prelude::BellyPlugin);
     app.insert_state(GameState::GenWorld);
     // app.add_plugins((menu::MenuPlugin));
-    app.configure_sets(Update, Playing.run_if(not(in_state(GameState::MainMenu))));
-    app.add_plugins(player_controller::PlayerPlugin);
-    app.add_systems(Update, settings::change_view_distance);
+    app.configure_sets(Update, Playing.run_if(in_state(GameState::Playing)));
+    app.add_plugins((
+        player_controller::PlayerPlugin,
+        health::HealthPlugin,
+        inventory::InventoryPlugin,
+    ));
     app.run();
 }
 
@@ -69,25 +123,10 @@ fn spawn_cube(mut commands: Commands, mut mesh: ResMut<Assets<Mesh>>, atlas: Res
         Transform::from_translation(Vec3::new(0., 256., 0.))
             .with_rotation(Quat::from_rotation_x(-0.3)),
     ));
-    commands.queue(textures::MakeTextureAtlas::new(
-        [
-            BlockType::Bedrock,
-            BlockType::Gravel,
-            BlockType::Dirt,
-            BlockType::Stone,
-            BlockType::Sand,
-            BlockType::GoldOre,
-            BlockType::IronOre,
-            BlockType::CoalOre,
-            // BlockType::DeadBush,
-            BlockType::Grass,
-            BlockType::Water,
-        ]
-        .into_iter(),
-    ));
+    commands.queue(textures::MakeTextureAtlas::new(BlockType::iter()));
 
     commands.spawn((
-        Mesh3d(mesh.add(blocks::make_test_block_mesh(4, 3))),
+        Mesh3d(mesh.add(blocks::make_test_block_mesh(4))),
         MeshMaterial3d(atlas.get_atlas()),
     ));
 }
@@ -1,24 +1,30 @@
 use bevy::prelude::*;
 
 use crate::{
+    actions::{Action, Actions},
     player_controller::{Player, PlayerCamera},
-    prelude::BlockId,
-    terrain::Map,
 };
 
+mod falling_blocks;
+mod query;
+mod ray;
+
+pub use query::{raycast, Sweep, VoxelHit, VoxelQuery};
+use ray::VoxelRayIter;
+
 pub struct PhysicsPlugin;
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PhysicsDebugRendering>()
+        app.add_plugins(falling_blocks::FallingBlockPlugin)
+            .init_resource::<PhysicsDebugRendering>()
             .add_systems(FixedUpdate, gravity)
             .add_systems(PreUpdate, update_grounded)
             .add_systems(Update, (apply_velocity, give_big_velocity, toggle_debug))
             .add_systems(
                 PostUpdate,
                 (
-                    render_player_collider
-                        .run_if(|r: Res<PhysicsDebugRendering>| r.render_colliders),
+                    render_colliders.run_if(|r: Res<PhysicsDebugRendering>| r.render_colliders),
                     render_velocity.run_if(|r: Res<PhysicsDebugRendering>| r.render_velocity),
                 ),
             );
@@ -32,8 +38,8 @@ struct PhysicsDebugRendering {
     render_velocity: bool,
 }
 
-fn toggle_debug(input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<PhysicsDebugRendering>) {
-    if input.just_pressed(KeyCode::F3) {
+fn toggle_debug(actions: Actions, mut settings: ResMut<PhysicsDebugRendering>) {
+    if actions.just_pressed(Action::ToggleDebug) {
         if settings.enabled {
             settings.enabled = false;
             settings.render_colliders = false;
@@ -41,10 +47,10 @@ fn toggle_debug(input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<PhysicsDe
             return;
         }
         settings.enabled = true;
-        if input.pressed(KeyCode::KeyC) {
+        if actions.pressed(Action::DebugColliders) {
             settings.render_colliders = true;
         }
-        if input.pressed(KeyCode::KeyV) {
+        if actions.pressed(Action::DebugVelocity) {
             settings.render_velocity = true;
         }
     }
@@ -54,102 +60,137 @@ fn toggle_debug(input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<PhysicsDe
 #[require(Transform, Velocity, PhysicsOutput)]
 pub struct PhysicsObject;
 
+/// Stops gravity and velocity from moving a [`PhysicsObject`]
+#[derive(Component)]
+pub struct Frozen;
+
+/// Stops gravity from pulling a [`PhysicsObject`] down
+#[derive(Component)]
+pub struct Flying;
+
 #[derive(Component, Default)]
 pub struct PhysicsOutput {
     grounded: bool,
+    just_landed: bool,
+    /// how fast the object was falling the last time it hit the ground
+    impact_speed: f32,
+}
+
+impl PhysicsOutput {
+    pub fn grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// returns how fast the object was falling on the frame it lands
+    pub fn just_landed(&self) -> Option<f32> {
+        self.just_landed.then_some(self.impact_speed)
+    }
 }
 
 #[derive(Component, Deref, DerefMut, Default)]
 pub struct Velocity(Vec3);
 
-fn update_grounded(map: Res<Map>, mut objects: Query<(&Transform, &mut PhysicsOutput)>) {
-    for (transform, mut output) in &mut objects {
-        let next = transform.translation - Vec3::new(0., 0.05, 0.);
-        output.grounded = map.get_block(BlockId::from_translation(next)).is_solid();
+/// An axis aligned box that collides a [`PhysicsObject`] with the blocks in the [`Map`](crate::terrain::Map)
+/// the box sits on top of the translation so the translation is at the objects feet
+#[derive(Component, Clone, Copy, Debug)]
+pub struct VoxelCollider {
+    pub half_extents: Vec3,
+}
+
+impl VoxelCollider {
+    pub const fn new(half_extents: Vec3) -> VoxelCollider {
+        VoxelCollider { half_extents }
+    }
+
+    pub fn center(&self, translation: Vec3) -> Vec3 {
+        translation + Vec3::Y * self.half_extents.y
+    }
+
+    /// returns the min and max corners of the box when the object is at translation
+    pub fn bounds(&self, translation: Vec3) -> (Vec3, Vec3) {
+        let center = self.center(translation);
+        (center - self.half_extents, center + self.half_extents)
+    }
+}
+
+/// objects without a [`VoxelCollider`] are grounded when the point at their feet is just above a block
+fn update_grounded(
+    voxels: VoxelQuery,
+    mut objects: Query<(&Transform, Option<&VoxelCollider>, &mut PhysicsOutput)>,
+) {
+    for (transform, collider, mut output) in &mut objects {
+        let collider = collider.copied().unwrap_or(VoxelCollider::new(Vec3::ZERO));
+        let (min, max) = collider.bounds(transform.translation);
+        let was_grounded = output.grounded;
+        output.grounded = voxels
+            .overlap_aabb(
+                Vec3::new(min.x, min.y - 0.05, min.z),
+                Vec3::new(max.x, min.y, max.z),
+            )
+            .next()
+            .is_some();
+        output.just_landed = output.grounded && !was_grounded;
     }
 }
 
 fn gravity(
-    mut players: Query<(&mut Velocity, &PhysicsOutput), With<PhysicsObject>>,
+    mut players: Query<
+        (&mut Velocity, &PhysicsOutput),
+        (With<PhysicsObject>, Without<Frozen>, Without<Flying>),
+    >,
     time: Res<Time>,
 ) {
     for (mut velocity, output) in &mut players {
         if !output.grounded {
             velocity.y += -9.8 * time.delta_secs();
-        } else {
+        } else if velocity.y < 0. {
+            // only stop falling so a jump can still leave the ground
             velocity.y = 0.;
         }
     }
 }
 
 pub fn apply_velocity(
-    map: Res<Map>,
-    mut players: Query<(&mut Transform, &mut Velocity), With<PhysicsObject>>,
+    voxels: VoxelQuery,
+    mut objects: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut PhysicsOutput,
+            Option<&VoxelCollider>,
+        ),
+        (With<PhysicsObject>, Without<Frozen>),
+    >,
     time: Res<Time>,
-    mut gizmos: Gizmos,
-    mut last: Local<Transform>,
 ) {
-    gizmos.cuboid(*last, Color::srgb(1., 0., 0.));
-    for (mut transform, mut velocity) in &mut players {
-        gizmos.cuboid(
-            Transform::from_translation(BlockId::from_translation(transform.translation).as_vec3()),
-            Color::srgb(0., 0., 1.),
-        );
+    for (mut transform, mut velocity, mut output, collider) in &mut objects {
         if velocity.length_squared() < 0.05 {
             continue;
         }
-        let mut next = transform.translation + velocity.0 * time.delta_secs();
-        let block = BlockId::from_translation(next);
-        let current = BlockId::from_translation(transform.translation);
-        let Some((face, path)) = ray_path(
-            transform.translation,
-            velocity.0,
-            (block - current).length_squared() as f32,
-            block,
-        ) else {
-            error!("No Path found");
+        let delta = velocity.0 * time.delta_secs();
+        velocity.0 *= 0.999;
+        let Some(collider) = collider else {
+            transform.translation += delta;
             continue;
         };
-        velocity.0 *= 0.999;
-        if !path.is_empty() {
-            let mut hit = None;
-            for block in path {
-                if map.get_block(block).is_solid() {
-                    hit = Some(block);
-                    break;
-                }
-            }
-            if let Some(hit) = hit {
-                if face.x > 0 {
-                    next.x = hit.x as f32 + 0.50;
-                } else if face.x < -0 {
-                    next.x = hit.x as f32 - 0.50;
-                }
-
-                if face.y > 0 {
-                    next.y = hit.y as f32 + 0.50;
-                } else if face.y < 0 {
-                    next.y = hit.y as f32 - 0.50;
-                }
-
-                if face.z > 0 {
-                    next.z = hit.z as f32 + 0.50;
-                } else if face.z < -0 {
-                    next.z = hit.z as f32 - 0.50;
-                }
-                velocity.0 = Vec3::ZERO;
-                last.translation = hit.as_vec3();
-            };
+        let (min, max) = collider.bounds(transform.translation);
+        let sweep = voxels.sweep_aabb(min, max, delta);
+        transform.translation += sweep.delta;
+        if sweep.hit.y && velocity.y < 0. {
+            output.impact_speed = -velocity.y;
         }
-        transform.translation = next;
+        velocity.0 = Vec3::select(sweep.hit, Vec3::ZERO, velocity.0);
     }
 }
 
-fn render_player_collider(mut gizmos: Gizmos, players: Query<&Transform, With<Player>>) {
-    let player = players.single();
-    let mut t = player.with_scale(Vec3::new(1., 2., 1.));
-    t.translation.y += 1.;
-    gizmos.cuboid(t, Color::srgb(1., 0., 1.));
+fn render_colliders(mut gizmos: Gizmos, objects: Query<(&GlobalTransform, &VoxelCollider)>) {
+    for (transform, collider) in &objects {
+        gizmos.cuboid(
+            Transform::from_translation(collider.center(transform.translation()))
+                .with_scale(collider.half_extents * 2.),
+            Color::srgb(1., 0., 1.),
+        );
+    }
 }
 
 fn render_velocity(mut gizmos: Gizmos, objects: Query<(&GlobalTransform, &Velocity)>) {
@@ -162,15 +203,15 @@ fn render_velocity(mut gizmos: Gizmos, objects: Query<(&GlobalTransform, &Veloci
 fn give_big_velocity(
     mut player: Query<(&PlayerCamera, &mut Velocity), With<Player>>,
     transforms: Query<&GlobalTransform>,
-    input: Res<ButtonInput<MouseButton>>,
+    actions: Actions,
     mut power: Local<f32>,
     time: Res<Time>,
 ) {
     for (transform, mut velocity) in &mut player {
-        if input.pressed(MouseButton::Right) {
+        if actions.pressed(Action::DebugLaunch) {
             *power += time.delta_secs();
         }
-        if input.just_released(MouseButton::Right) {
+        if actions.just_released(Action::DebugLaunch) {
             let transform = transforms
                 .get(transform.get())
                 .expect("Player to have camera");
@@ -179,99 +220,3 @@ fn give_big_velocity(
         }
     }
 }
-
-fn ray_path(
-    mut origin: Vec3,
-    mut direction: Vec3,
-    radius: f32,
-    end: BlockId,
-) -> Option<(IVec3, Vec<BlockId>)> {
-    #[inline]
-    fn int_bound(s: f32, ds: f32) -> f32 {
-        if (ds < 0.) {
-            int_bound(-s, -ds)
-        } else {
-            let s = (s % 1. + 1.) % 1.;
-            (1. - s) / ds
-        }
-    }
-    let max = (BlockId::from_translation(origin) - end).length_squared() as usize;
-    direction = direction.normalize();
-    let step_x = direction.x.signum();
-    let step_y = direction.y.signum();
-    let step_z = direction.z.signum();
-    let mut t_max_x = int_bound(origin.x, direction.x);
-    let mut t_max_y = int_bound(origin.y, direction.y);
-    let mut t_max_z = int_bound(origin.z, direction.z);
-    let t_delta_x = step_x / direction.x;
-    let t_delta_y = step_y / direction.y;
-    let t_delta_z = step_z / direction.z;
-
-    if direction.x == 0. && direction.y == 0. && direction.z == 0. {
-        error!("Raycast in zero direction");
-        return None;
-    }
-    let mut out = Vec::new();
-    let mut face = IVec3::ZERO;
-    while out.len() < max {
-        if (t_max_x < t_max_y) {
-            if (t_max_x < t_max_z) {
-                if t_delta_x > radius {
-                    break;
-                };
-                // Update which cube we are now in.
-                origin.x += step_x;
-                // Adjust tMaxX to the next X-oriented boundary crossing.
-                t_max_x += t_delta_x;
-                // Record the normal vector of the cube face we entered.
-                face = IVec3::new(-step_x as i32, 0, 0);
-                let add = BlockId::from_translation(origin);
-                out.push(add);
-                if add == end {
-                    break;
-                }
-            } else {
-                if (t_max_z > radius) {
-                    break;
-                }
-                origin.z += step_z;
-                t_max_z += t_delta_z;
-                face = IVec3::new(0, 0, -step_z as i32);
-                let add = BlockId::from_translation(origin);
-                out.push(add);
-                if add == end {
-                    break;
-                }
-            }
-        } else {
-            if (t_max_y < t_max_z) {
-                if (t_max_y > radius) {
-                    break;
-                };
-                origin.y += step_y;
-                t_max_y += t_delta_y;
-                face = IVec3::new(0, -step_y as i32, 0);
-                let add = BlockId::from_translation(origin);
-                out.push(add);
-                if add == end {
-                    break;
-                }
-            } else {
-                // Identical to the second case, repeated for simplicity in
-                // the conditionals.
-                if (t_max_z > radius) {
-                    break;
-                };
-                origin.z += step_z;
-                t_max_z += t_delta_z;
-                face = IVec3::new(0, 0, -step_z as i32);
-                let add = BlockId::from_translation(origin);
-                out.push(add);
-                if add == end {
-                    break;
-                }
-            }
-        }
-    }
-    Some((face, out))
-}
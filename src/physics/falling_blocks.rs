@@ -0,0 +1,193 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use super::{apply_velocity, Frozen, PhysicsObject, PhysicsOutput, VoxelCollider};
+use crate::{prelude::*, terrain::Map, textures::BlockTexturesChanged};
+
+pub struct FallingBlockPlugin;
+
+impl Plugin for FallingBlockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (start_falling, land_falling_blocks.after(apply_velocity)),
+        );
+    }
+}
+
+/// A block that has been taken out of the [`Map`] while it falls
+/// the transform is at the bottom of the block the same as the player
+#[derive(Component)]
+#[require(PhysicsObject)]
+pub struct FallingBlock(BlockType);
+
+/// checks every changed block for blocks with gravity that no longer have anything under them
+/// a whole column is started at once so stacks of sand don't take a frame per block
+/// the meshes have the texture layer baked in so they are made again when the textures change
+fn start_falling(
+    mut commands: Commands,
+    mut map: ResMut<Map>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    atlas: Res<TextureHandles>,
+    mut textures_changed: EventReader<BlockTexturesChanged>,
+    mut cache: Local<HashMap<BlockType, Handle<Mesh>>>,
+) {
+    if textures_changed.read().count() > 0 {
+        cache.clear();
+    }
+    if map.changed_blocks.is_empty() {
+        return;
+    }
+    let changed = std::mem::take(&mut map.changed_blocks);
+    for block in changed {
+        let column = falling_column(block, |block| map.get_block(block));
+        for (current, falling) in column {
+            map.set_block(current, BlockType::Air);
+            let mesh = cache
+                .entry(falling)
+                .or_insert_with(|| {
+                    let layer = atlas.get_indexes(&falling).first().copied().unwrap_or(0);
+                    meshes.add(crate::blocks::make_test_block_mesh(layer as u32))
+                })
+                .clone();
+            commands
+                .spawn((
+                    FallingBlock(falling),
+                    VoxelCollider::new(Vec3::splat(0.49)),
+                    Transform::from_translation(current.to_vec3() - Vec3::Y * 0.5),
+                    Visibility::default(),
+                    Name::new(format!("Falling {:?}", falling)),
+                ))
+                .with_children(|p| {
+                    p.spawn((
+                        Mesh3d(mesh),
+                        MeshMaterial3d(atlas.get_atlas()),
+                        Transform::from_translation(Vec3::Y * 0.5),
+                    ));
+                });
+        }
+    }
+}
+
+/// the blocks that start falling after block changed, bottom first
+/// once the bottom one falls every block with gravity stacked on it falls too
+fn falling_column(
+    block: BlockId,
+    get_block: impl Fn(BlockId) -> BlockType,
+) -> Vec<(BlockId, BlockType)> {
+    let mut current = if get_block(block).has_gravity() {
+        block
+    } else {
+        block.get(Direction::Up)
+    };
+    let mut column = Vec::new();
+    if get_block(current.get(Direction::Down)).is_solid() {
+        return column;
+    }
+    loop {
+        let falling = get_block(current);
+        if !falling.has_gravity() {
+            return column;
+        }
+        column.push((current, falling));
+        current = current.get(Direction::Up);
+    }
+}
+
+/// puts falling blocks back into the [`Map`] once they hit the ground
+/// blocks over chunks that aren't loaded wait frozen so they aren't lost or fall through the world
+fn land_falling_blocks(
+    mut commands: Commands,
+    mut map: ResMut<Map>,
+    blocks: Query<(
+        Entity,
+        &Transform,
+        &PhysicsOutput,
+        &FallingBlock,
+        Has<Frozen>,
+    )>,
+) {
+    for (entity, transform, output, block, frozen) in &blocks {
+        let position = BlockId::from_translation(transform.translation + Vec3::Y * 0.5);
+        let loaded = map.is_loaded(&position.into());
+        if loaded == frozen {
+            if loaded {
+                commands.entity(entity).remove::<Frozen>();
+            } else {
+                commands.entity(entity).insert(Frozen);
+            }
+        }
+        if !loaded || !output.grounded {
+            continue;
+        }
+        let Some(target) = landing_spot(
+            position,
+            |chunk| map.is_loaded(&chunk),
+            |block| map.get_block(block).is_solid(),
+        ) else {
+            continue;
+        };
+        map.set_block(target, block.0);
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// the first free block from position up, another block could have landed there first
+/// none if that is in a chunk that isn't loaded so the block waits rather than being lost
+fn landing_spot(
+    mut position: BlockId,
+    is_loaded: impl Fn(ChunkId) -> bool,
+    is_solid: impl Fn(BlockId) -> bool,
+) -> Option<BlockId> {
+    while is_solid(position) {
+        position = position.get(Direction::Up);
+    }
+    is_loaded(position.into()).then_some(position)
+}
+
+#[test]
+fn test_falling_column() {
+    let column = |blocks: &'static [BlockType]| {
+        move |block: BlockId| {
+            usize::try_from(block.y())
+                .ok()
+                .and_then(|y| blocks.get(y).copied())
+                .unwrap_or(BlockType::Air)
+        }
+    };
+    // digging out the stone under a stack of sand and gravel drops all of it
+    let stack = column(&[
+        BlockType::Stone,
+        BlockType::Air,
+        BlockType::Sand,
+        BlockType::Gravel,
+        BlockType::Sand,
+        BlockType::Dirt,
+        BlockType::Sand,
+    ]);
+    assert_eq!(
+        falling_column(BlockId::new(0, 1, 0), stack),
+        [
+            (BlockId::new(0, 2, 0), BlockType::Sand),
+            (BlockId::new(0, 3, 0), BlockType::Gravel),
+            (BlockId::new(0, 4, 0), BlockType::Sand),
+        ]
+    );
+    // sand on something solid stays put
+    let supported = column(&[BlockType::Stone, BlockType::Sand, BlockType::Sand]);
+    assert!(falling_column(BlockId::new(0, 2, 0), supported).is_empty());
+    assert!(falling_column(BlockId::new(0, 0, 0), supported).is_empty());
+}
+
+#[test]
+fn test_landing_spot() {
+    let loaded = |chunk: ChunkId| chunk.y() == 0;
+    let floor = |block: BlockId| block.y() < 3;
+    assert_eq!(
+        landing_spot(BlockId::new(0, 1, 0), loaded, floor),
+        Some(BlockId::new(0, 3, 0))
+    );
+    // stacked up into a chunk that isn't loaded it has to wait
+    let tall = |block: BlockId| block.y() < CHUNK_SIZE;
+    assert_eq!(landing_spot(BlockId::new(0, 1, 0), loaded, tall), None);
+    assert_eq!(landing_spot(BlockId::new(0, 1, 0), |_| false, floor), None);
+}
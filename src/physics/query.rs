@@ -0,0 +1,409 @@
+use std::{borrow::Cow, ops::Deref};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::VoxelRayIter;
+use crate::{blocks::ModelBox, prelude::*, terrain::Map};
+
+/// Read only queries against the blocks in the [`Map`]
+/// blocks are centered on their [`BlockId`] so block `i` covers `i - 0.5..i + 0.5`
+#[derive(SystemParam)]
+pub struct VoxelQuery<'w> {
+    map: Res<'w, Map>,
+}
+
+impl VoxelQuery<'_> {
+    pub fn is_solid(&self, block: BlockId) -> bool {
+        self.map.get_block(block).is_solid()
+    }
+
+    /// returns true if the block containing point is solid
+    pub fn is_solid_at(&self, point: Vec3) -> bool {
+        self.is_solid(BlockId::from_translation(point))
+    }
+
+    /// casts a ray returning the first block whose model it hits within max distance, max can be infinite
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max: f32) -> Option<VoxelHit> {
+        raycast(
+            |block| {
+                self.map
+                    .get_block(block)
+                    .target_boxes(self.map.get_state(block))
+            },
+            origin,
+            direction,
+            max,
+        )
+    }
+
+    /// the boxes of a block things collide with turned the way it was placed, empty if it isn't solid
+    pub fn collision_boxes(&self, block: BlockId) -> Cow<'static, [ModelBox]> {
+        let state = self.map.get_state(block);
+        let block = self.map.get_block(block);
+        if !block.is_solid() {
+            return Cow::Borrowed(&[]);
+        }
+        match block.state_shape(state) {
+            Cow::Borrowed(shape) => Cow::Borrowed(&shape.boxes),
+            Cow::Owned(shape) => Cow::Owned(shape.boxes),
+        }
+    }
+
+    /// moves the box between min and max by delta stopping flush against the model of any solid block
+    pub fn sweep_aabb(&self, min: Vec3, max: Vec3, delta: Vec3) -> Sweep {
+        sweep_boxes(|block| self.collision_boxes(block), min, max, delta)
+    }
+
+    /// returns every solid block whose model overlaps the box between min and max
+    pub fn overlap_aabb(&self, min: Vec3, max: Vec3) -> impl Iterator<Item = BlockId> + '_ {
+        blocks_in_aabb(min, max).filter(move |block| {
+            self.collision_boxes(*block)
+                .iter()
+                .any(|model_box| overlaps(*block, model_box, min, max))
+        })
+    }
+}
+
+/// The result of [`VoxelQuery::raycast`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoxelHit {
+    pub block: BlockId,
+    /// the face the ray entered the block through, none if the ray started inside the block
+    pub face: Option<Direction>,
+    pub distance: f32,
+}
+
+/// The result of [`VoxelQuery::sweep_aabb`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sweep {
+    /// how far the box was able to move
+    pub delta: Vec3,
+    /// the axes that hit a solid block
+    pub hit: BVec3,
+}
+
+/// returns every block the box between min and max overlaps
+/// a box that is only touching a block does not overlap it
+fn blocks_in_aabb(min: Vec3, max: Vec3) -> impl Iterator<Item = BlockId> {
+    let min = (min + 0.5).floor().as_ivec3();
+    let max = (max + 0.5).ceil().as_ivec3() - 1;
+    (min.y..=max.y).flat_map(move |y| {
+        (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| BlockId::new(x, y, z)))
+    })
+}
+
+/// the most blocks a ray walks through so a ray with no max distance over open terrain still ends
+const MAX_RAY_BLOCKS: usize = 4096;
+
+/// casts a ray against the boxes of each block it passes through, see [`VoxelQuery::raycast`]
+/// rays pass through the empty parts of blocks like the top of a slab
+pub fn raycast<B: Deref<Target = [ModelBox]>>(
+    boxes: impl Fn(BlockId) -> B,
+    origin: Vec3,
+    direction: Vec3,
+    max: f32,
+) -> Option<VoxelHit> {
+    let Some(ray) = VoxelRayIter::new(origin, direction, max) else {
+        error!("Raycast in zero direction");
+        return None;
+    };
+    let direction = direction.normalize();
+    ray.take(MAX_RAY_BLOCKS).find_map(|(block, _, _)| {
+        let center = block.to_vec3();
+        boxes(block)
+            .iter()
+            .filter_map(|model_box| {
+                ray_box(
+                    origin,
+                    direction,
+                    center + model_box.min(),
+                    center + model_box.max(),
+                )
+            })
+            .filter(|(_, distance)| *distance <= max)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(face, distance)| VoxelHit {
+                block,
+                face,
+                distance,
+            })
+    })
+}
+
+/// the face a ray enters the box between min and max through and how far along the ray that is
+/// the face is none if the ray starts inside the box
+fn ray_box(
+    origin: Vec3,
+    direction: Vec3,
+    min: Vec3,
+    max: Vec3,
+) -> Option<(Option<Direction>, f32)> {
+    let (mut enter, mut exit, mut entry_axis) = (f32::NEG_INFINITY, f32::INFINITY, None);
+    for axis in 0..3 {
+        // dividing by zero would make nan on the box's edges, a parallel ray just has to be between them
+        if direction[axis] == 0. {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - origin[axis]) / direction[axis];
+        let b = (max[axis] - origin[axis]) / direction[axis];
+        if a.min(b) > enter {
+            enter = a.min(b);
+            entry_axis = Some(axis);
+        }
+        exit = exit.min(a.max(b));
+    }
+    if exit < enter.max(0.) {
+        return None;
+    }
+    if enter <= 0. {
+        return Some((None, 0.));
+    }
+    let axis = entry_axis?;
+    let mut normal = Vec3::ZERO;
+    normal[axis] = -direction[axis].signum();
+    Some((Some(Direction::from_vec(normal)), enter))
+}
+
+/// true if a box of a block's model overlaps the box between min and max, touching is not overlapping
+fn overlaps(block: BlockId, model_box: &ModelBox, min: Vec3, max: Vec3) -> bool {
+    let center = block.to_vec3();
+    (center + model_box.min()).cmplt(max).all() && (center + model_box.max()).cmpgt(min).all()
+}
+
+fn sweep_boxes<B: Deref<Target = [ModelBox]>>(
+    boxes: impl Fn(BlockId) -> B,
+    min: Vec3,
+    max: Vec3,
+    delta: Vec3,
+) -> Sweep {
+    let start = min;
+    let mut min = min;
+    let mut max = max;
+    let mut hit = [false; 3];
+    // move up and down first so objects land before sliding into walls
+    for axis in [1, 0, 2] {
+        // step at most half a block at a time so fast objects can't skip over blocks
+        let steps = (delta[axis].abs() / 0.5).ceil();
+        let step = delta[axis] / steps.max(1.);
+        for _ in 0..steps as usize {
+            let mut next_min = min;
+            let mut next_max = max;
+            next_min[axis] += step;
+            next_max[axis] += step;
+            // boxes it is already well inside of are ignored so it can get back out
+            let mut hits = Vec::new();
+            for block in blocks_in_aabb(next_min, next_max) {
+                for model_box in boxes(block).iter() {
+                    if overlaps(block, model_box, next_min, next_max)
+                        && !overlaps(block, model_box, min + 1e-3, max - 1e-3)
+                    {
+                        let center = block.to_vec3();
+                        hits.push((center + model_box.min(), center + model_box.max()));
+                    }
+                }
+            }
+            if hits.is_empty() {
+                min = next_min;
+                max = next_max;
+                continue;
+            }
+            // snap flush against the face of the nearest box that was hit
+            let snap = if step > 0. {
+                hits.iter()
+                    .map(|(hit_min, _)| hit_min[axis] - max[axis])
+                    .fold(f32::INFINITY, f32::min)
+            } else {
+                hits.iter()
+                    .map(|(_, hit_max)| hit_max[axis] - min[axis])
+                    .fold(f32::NEG_INFINITY, f32::max)
+            };
+            min[axis] += snap;
+            max[axis] += snap;
+            hit[axis] = true;
+            break;
+        }
+    }
+    Sweep {
+        delta: min - start,
+        hit: BVec3::from(hit),
+    }
+}
+
+#[cfg(test)]
+const FULL: &[ModelBox] = &[ModelBox::FULL];
+
+#[cfg(test)]
+fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() < 1e-4, "{a} != {b}");
+}
+
+#[test]
+fn test_raycast_axis_aligned() {
+    let wall = |block: BlockId| if block.x() == 3 { FULL } else { &[] };
+    let hit = raycast(wall, Vec3::ZERO, Vec3::X, 10.).expect("to hit the wall");
+    assert_eq!(hit.block, BlockId::new(3, 0, 0));
+    assert_eq!(hit.face, Some(Direction::Left));
+    assert_near(hit.distance, 2.5);
+
+    let wall = |block: BlockId| if block.z() == 3 { FULL } else { &[] };
+    let hit = raycast(wall, Vec3::ZERO, Vec3::Z, 10.).expect("to hit the wall");
+    assert_eq!(hit.block, BlockId::new(0, 0, 3));
+    assert_eq!(hit.face, Some(Direction::Back));
+    assert_near(hit.distance, 2.5);
+}
+
+#[test]
+fn test_raycast_negative_direction() {
+    let wall = |block: BlockId| if block.x() == -3 { FULL } else { &[] };
+    let hit = raycast(wall, Vec3::ZERO, Vec3::NEG_X, 10.).expect("to hit the wall");
+    assert_eq!(hit.block, BlockId::new(-3, 0, 0));
+    assert_eq!(hit.face, Some(Direction::Right));
+    assert_near(hit.distance, 2.5);
+
+    let floor = |block: BlockId| if block.y() <= 0 { FULL } else { &[] };
+    let hit =
+        raycast(floor, Vec3::new(0.2, 5.2, -0.3), Vec3::NEG_Y, 10.).expect("to hit the floor");
+    assert_eq!(hit.block, BlockId::new(0, 0, 0));
+    assert_eq!(hit.face, Some(Direction::Up));
+    assert_near(hit.distance, 4.7);
+
+    // starting exactly on a block boundary
+    let floor = |block: BlockId| if block.y() <= 0 { FULL } else { &[] };
+    let hit = raycast(floor, Vec3::new(0., 2.5, 0.), Vec3::NEG_Y, 10.).expect("to hit the floor");
+    assert_eq!(hit.block, BlockId::new(0, 0, 0));
+    assert_near(hit.distance, 2.);
+
+    let corner = |block: BlockId| {
+        if block.x() <= -2 && block.y() <= -2 {
+            FULL
+        } else {
+            &[]
+        }
+    };
+    let hit = raycast(corner, Vec3::ZERO, Vec3::new(-1., -1., 0.), 10.).expect("to hit the corner");
+    assert!(hit.block.x() <= -2 && hit.block.y() <= -2);
+}
+
+#[test]
+fn test_raycast_along_block_edge() {
+    // a ray in the plane between two columns of blocks still hits them
+    let wall = |block: BlockId| if block.z() == 3 { FULL } else { &[] };
+    let hit = raycast(wall, Vec3::new(-0.5, 0., 0.), Vec3::Z, 10.).expect("to hit the wall");
+    assert_eq!(hit.face, Some(Direction::Back));
+    assert_near(hit.distance, 2.5);
+    assert!([BlockId::new(-1, 0, 3), BlockId::new(0, 0, 3)].contains(&hit.block));
+    let hit = raycast(wall, Vec3::new(-0.5, 0.5, 0.), Vec3::Z, 10.).expect("to hit the wall");
+    assert_near(hit.distance, 2.5);
+}
+
+#[test]
+fn test_raycast_limits() {
+    let wall = |block: BlockId| if block.x() == 3 { FULL } else { &[] };
+    assert_eq!(raycast(wall, Vec3::ZERO, Vec3::X, 2.), None);
+    assert_eq!(raycast(wall, Vec3::ZERO, Vec3::NEG_X, 100.), None);
+    assert_eq!(raycast(wall, Vec3::ZERO, Vec3::ZERO, 100.), None);
+    // an endless ray that never hits anything still stops
+    assert_eq!(raycast(wall, Vec3::ZERO, Vec3::NEG_X, f32::INFINITY), None);
+    let far = |block: BlockId| {
+        if block.x() == MAX_RAY_BLOCKS as i32 * 2 {
+            FULL
+        } else {
+            &[]
+        }
+    };
+    assert_eq!(raycast(far, Vec3::ZERO, Vec3::X, f32::INFINITY), None);
+
+    let hit = raycast(wall, Vec3::new(3.2, 0., 0.), Vec3::X, 10.).expect("to start in the wall");
+    assert_eq!(hit.face, None);
+    assert_near(hit.distance, 0.);
+}
+
+#[test]
+fn test_sweep_full_blocks() {
+    let floor = |block: BlockId| if block.y() <= 0 { FULL } else { &[] };
+    let sweep = sweep_boxes(
+        floor,
+        Vec3::new(-0.3, 3., -0.3),
+        Vec3::new(0.3, 4.8, 0.3),
+        Vec3::new(0., -10., 0.),
+    );
+    assert!(sweep.hit.y);
+    assert!(!sweep.hit.x && !sweep.hit.z);
+    assert_near(sweep.delta.y, -2.5);
+
+    let wall = |block: BlockId| if block.x() == -2 { FULL } else { &[] };
+    let sweep = sweep_boxes(
+        wall,
+        Vec3::new(-0.3, 0., -0.3),
+        Vec3::new(0.3, 1.8, 0.3),
+        Vec3::new(-5., 0., 1.),
+    );
+    assert!(sweep.hit.x);
+    assert_near(sweep.delta.x, -1.2);
+    assert_near(sweep.delta.z, 1.);
+}
+
+#[test]
+fn test_blocks_in_aabb_touching() {
+    let blocks = blocks_in_aabb(Vec3::splat(-0.5), Vec3::splat(0.5)).collect::<Vec<_>>();
+    assert_eq!(blocks, vec![BlockId::new(0, 0, 0)]);
+    let blocks = blocks_in_aabb(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.6, 0.5, 0.5)).count();
+    assert_eq!(blocks, 2);
+}
+
+#[test]
+fn test_sweep_model_boxes() {
+    const SLAB: &[ModelBox] = &[ModelBox::new([0., 0., 0.], [16., 8., 16.])];
+    let slabs = |block: BlockId| if block.y() == 0 { SLAB } else { &[] };
+    let sweep = sweep_boxes(
+        slabs,
+        Vec3::new(-0.3, 3., -0.3),
+        Vec3::new(0.3, 4.8, 0.3),
+        Vec3::new(0., -10., 0.),
+    );
+    assert!(sweep.hit.y);
+    // lands on the top of the slab in the middle of the block
+    assert_near(sweep.delta.y, -3.);
+
+    // walking into the side of a slab from above it doesn't hit
+    let sweep = sweep_boxes(
+        slabs,
+        Vec3::new(-0.3, 0., -0.3),
+        Vec3::new(0.3, 1.8, 0.3),
+        Vec3::new(5., 0., 0.),
+    );
+    assert!(!sweep.hit.x);
+    assert_near(sweep.delta.x, 5.);
+}
+
+#[test]
+fn test_raycast_model_boxes() {
+    const SLAB: &[ModelBox] = &[ModelBox::new([0., 0., 0.], [16., 8., 16.])];
+    let slab = |block: BlockId| {
+        if block == BlockId::new(2, 0, 0) {
+            SLAB
+        } else {
+            &[]
+        }
+    };
+    // over the top half of the slab and out the other side
+    assert_eq!(raycast(slab, Vec3::new(0., 0.25, 0.), Vec3::X, 10.), None);
+    let hit = raycast(slab, Vec3::new(2., 3., 0.), Vec3::NEG_Y, 10.).expect("to hit the slab");
+    assert_eq!(hit.block, BlockId::new(2, 0, 0));
+    assert_eq!(hit.face, Some(Direction::Up));
+    assert_near(hit.distance, 3.);
+
+    // the air around a plant is passed through
+    let plant = BlockType::TallGrass.target_boxes(BlockState::DEFAULT);
+    let plants = |block: BlockId| if block.x() == 2 { &plant[..] } else { &[] };
+    assert_eq!(raycast(plants, Vec3::new(0., 0., 0.45), Vec3::X, 10.), None);
+    let hit = raycast(plants, Vec3::ZERO, Vec3::X, 10.).expect("to hit the plant");
+    assert_eq!(hit.face, Some(Direction::Left));
+    assert_near(hit.distance, 2. - 6. / 16.);
+    assert!(BlockType::Water
+        .target_boxes(BlockState::DEFAULT)
+        .is_empty());
+}
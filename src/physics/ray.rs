@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+
+use crate::prelude::*;
+
+/// Walks every block a ray passes through in order
+/// yields the block, the face the ray entered it through and the distance along the ray it was entered at
+/// the first block is the one the ray starts in, it has no entry face and a distance of 0
+pub struct VoxelRayIter {
+    block: IVec3,
+    step: IVec3,
+    t_max: Vec3,
+    t_delta: Vec3,
+    max: f32,
+    face: Option<Direction>,
+    distance: f32,
+    done: bool,
+}
+
+impl VoxelRayIter {
+    /// returns None if direction is zero
+    pub fn new(origin: Vec3, direction: Vec3, max: f32) -> Option<VoxelRayIter> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return None;
+        }
+        // blocks are centered on their BlockId so shift by half a block so block i covers i..i+1
+        let origin = origin + 0.5;
+        let block = origin.floor().as_ivec3();
+        let mut step = IVec3::ZERO;
+        let mut t_max = Vec3::INFINITY;
+        let mut t_delta = Vec3::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] > 0. {
+                step[axis] = 1;
+                t_max[axis] = (block[axis] as f32 + 1. - origin[axis]) / direction[axis];
+                t_delta[axis] = 1. / direction[axis];
+            } else if direction[axis] < 0. {
+                step[axis] = -1;
+                t_max[axis] = (origin[axis] - block[axis] as f32) / -direction[axis];
+                t_delta[axis] = 1. / -direction[axis];
+            }
+        }
+        Some(VoxelRayIter {
+            block,
+            step,
+            t_max,
+            t_delta,
+            max,
+            face: None,
+            distance: 0.,
+            done: false,
+        })
+    }
+}
+
+impl Iterator for VoxelRayIter {
+    type Item = (BlockId, Option<Direction>, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let out = (
+            BlockId::new(self.block.x, self.block.y, self.block.z),
+            self.face,
+            self.distance,
+        );
+        let axis = if self.t_max.x < self.t_max.y && self.t_max.x < self.t_max.z {
+            0
+        } else if self.t_max.y < self.t_max.z {
+            1
+        } else {
+            2
+        };
+        if self.t_max[axis] > self.max {
+            self.done = true;
+        } else {
+            self.distance = self.t_max[axis];
+            self.block[axis] += self.step[axis];
+            self.t_max[axis] += self.t_delta[axis];
+            self.face = Some(Direction::entered(axis, self.step[axis]));
+        }
+        Some(out)
+    }
+}
+
+#[cfg(test)]
+fn contains(block: BlockId, point: Vec3) -> bool {
+    (point - block.to_vec3()).abs().max_element() <= 0.5 + 1e-3
+}
+
+#[test]
+fn test_ray_iter_axis_aligned() {
+    let blocks = VoxelRayIter::new(Vec3::ZERO, Vec3::NEG_X, 2.6)
+        .expect("direction to not be zero")
+        .collect::<Vec<_>>();
+    assert_eq!(
+        blocks,
+        vec![
+            (BlockId::new(0, 0, 0), None, 0.),
+            (BlockId::new(-1, 0, 0), Some(Direction::Right), 0.5),
+            (BlockId::new(-2, 0, 0), Some(Direction::Right), 1.5),
+            (BlockId::new(-3, 0, 0), Some(Direction::Right), 2.5),
+        ]
+    );
+    assert!(VoxelRayIter::new(Vec3::ZERO, Vec3::ZERO, 10.).is_none());
+}
+
+/// compares the blocks the iterator walks with the blocks found by sampling lots of points along random rays
+#[test]
+fn test_ray_iter_against_sampling() {
+    use rand::{Rng, SeedableRng};
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    const MAX: f32 = 12.;
+    const SAMPLE: f32 = 0.001;
+    for _ in 0..200 {
+        let origin = Vec3::new(
+            rng.gen_range(-20.0..20.0),
+            rng.gen_range(-20.0..20.0),
+            rng.gen_range(-20.0..20.0),
+        );
+        let mut direction = Vec3::new(
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+            rng.gen_range(-1.0..1.0),
+        );
+        // make sure axis aligned and flat rays get tested as well
+        for axis in 0..3 {
+            if rng.gen_bool(0.2) {
+                direction[axis] = 0.;
+            }
+        }
+        let Some(ray) = VoxelRayIter::new(origin, direction, MAX) else {
+            continue;
+        };
+        let direction = direction.normalize();
+        let walked = ray.collect::<Vec<_>>();
+
+        // every block entered must contain the point it was entered at
+        // and be next to the block before it on the side of the entry face
+        for pair in walked.windows(2) {
+            let (last, _, _) = pair[0];
+            let (block, face, t) = pair[1];
+            let face = face.expect("only the first block has no face");
+            assert!(t >= pair[0].2 && t <= MAX);
+            assert!(contains(block, origin + direction * t));
+            assert!(block.get(face) == last);
+        }
+
+        // every sampled block must be walked in the same order
+        let mut walked = walked.iter().map(|(block, _, _)| *block);
+        let mut t = 0.;
+        while t < MAX {
+            let point = origin + direction * t;
+            let sampled = walked
+                .by_ref()
+                .find(|block| contains(*block, point))
+                .unwrap_or_else(|| panic!("{:?} was sampled but not walked", point));
+            // skip over the rest of the samples in this block
+            while t < MAX && contains(sampled, origin + direction * t) {
+                t += SAMPLE;
+            }
+        }
+    }
+}
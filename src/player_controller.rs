@@ -1,80 +1,116 @@
 use avian3d::prelude::*;
 use bevy::{
     color::palettes::css::BLUE,
-    input::mouse::{AccumulatedMouseMotion, MouseMotion, MouseWheel},
+    input::mouse::AccumulatedMouseMotion,
     prelude::*,
     window::{CursorGrabMode, PrimaryWindow},
 };
-use strum::EnumCount;
 
 use crate::{
-    cam::{KeyBindings, MovementSettings},
-    physics::PhysicsObject,
-    prelude::{BlockId, BlockType, ChunkId, CHUNK_SIZE, GROUND_HEIGHT},
+    actions::{Action, Actions},
+    cam::MovementSettings,
+    health::{Breath, Health},
+    inventory::{Inventory, ItemStack},
+    physics::{
+        apply_velocity, raycast, Flying, Frozen, PhysicsObject, PhysicsOutput, Velocity,
+        VoxelCollider, VoxelHit, VoxelQuery,
+    },
+    prelude::{BlockId, BlockState, ChunkId, Direction, CHUNK_SIZE, GROUND_HEIGHT},
+    settings::ViewDistance,
     terrain::Map,
     GameState, Playing,
 };
 
+mod breaking;
+
+pub use breaking::BreakProgress;
+
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
-            .add_systems(
-                Update,
-                (noclip, player_look, player_laser, set_selected).in_set(Playing),
-            )
+        app.add_plugins(breaking::BreakingPlugin)
+            .add_systems(Startup, spawn_player)
             .add_systems(
                 Update,
-                player_move
-                    .chain()
-                    .in_set(Playing)
-                    .run_if(in_state(PlayerMode::Normal)),
+                update_fog.run_if(resource_exists_and_changed::<ViewDistance>),
             )
+            .add_systems(Update, (place_spawning_players, unfreeze_players).chain())
             .add_systems(
                 Update,
-                noclip_move
-                    .in_set(Playing)
-                    .run_if(in_state(PlayerMode::NoClip)),
+                (
+                    (cycle_game_mode, apply_game_mode).chain(),
+                    player_laser,
+                    // nothing to look or move with when running headless
+                    (
+                        player_look,
+                        // the jump has to be in the velocity before it moves the player
+                        player_move.before(apply_velocity),
+                        noclip_move,
+                    )
+                        .run_if(any_with_component::<PrimaryWindow>),
+                )
+                    .in_set(Playing),
             )
-            .init_resource::<SelectedBlock>()
-            .init_state::<PlayerMode>();
+            .init_resource::<SpawnPoint>();
     }
 }
 
-#[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone)]
-enum PlayerMode {
+/// How a player interacts with the world
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    /// blocks are used up when placed, breaking takes time and players take damage
     #[default]
-    Normal,
-    NoClip,
+    Survival,
+    /// blocks are never used up, breaking is instant and players can fly
+    Creative,
+    /// players fly through blocks and can't interact with the world
+    Spectator,
+}
+
+impl GameMode {
+    pub fn next(&self) -> GameMode {
+        match self {
+            GameMode::Survival => GameMode::Creative,
+            GameMode::Creative => GameMode::Spectator,
+            GameMode::Spectator => GameMode::Survival,
+        }
+    }
+
+    pub fn takes_damage(&self) -> bool {
+        *self == GameMode::Survival
+    }
+
+    pub fn can_fly(&self) -> bool {
+        *self != GameMode::Survival
+    }
+}
+
+fn cycle_game_mode(actions: Actions, mut players: Query<&mut GameMode, With<Player>>) {
+    if actions.just_pressed(Action::CycleGameMode) {
+        for mut mode in &mut players {
+            *mode = mode.next();
+            info!("Game mode: {:?}", *mode);
+        }
+    }
 }
 
-fn noclip(
-    mut players: Query<&mut RigidBody, With<Player>>,
-    input: Res<ButtonInput<KeyCode>>,
-    state: Res<State<PlayerMode>>,
-    mut next: ResMut<NextState<PlayerMode>>,
+fn apply_game_mode(
+    mut commands: Commands,
+    mut players: Query<(Entity, &GameMode, &mut Velocity), Changed<GameMode>>,
 ) {
-    if input.just_pressed(KeyCode::F12) {
-        match state.get() {
-            PlayerMode::Normal => {
-                for mut p in &mut players {
-                    *p = RigidBody::Kinematic;
-                }
-                next.set(PlayerMode::NoClip);
-            }
-            PlayerMode::NoClip => {
-                for mut p in &mut players {
-                    *p = RigidBody::Dynamic;
-                }
-                next.set(PlayerMode::Normal);
-            }
+    for (player, mode, mut velocity) in &mut players {
+        if mode.can_fly() {
+            **velocity = Vec3::ZERO;
+            commands.entity(player).insert(Flying);
+        } else {
+            commands.entity(player).remove::<Flying>();
         }
     }
 }
 
 #[derive(Component)]
-#[require(PhysicsObject)]
+#[require(PhysicsObject, Health, Breath, Inventory, GameMode, BreakProgress)]
 pub struct Player;
 
 #[derive(Component, Deref)]
@@ -86,29 +122,11 @@ impl PlayerCamera {
     }
 }
 
-#[derive(Resource, Default)]
-pub struct SelectedBlock(BlockType);
-
-impl SelectedBlock {
-    pub fn set(&mut self, block: BlockType) {
-        self.0 = block;
-    }
-
-    pub fn get(&self) -> BlockType {
-        self.0
-    }
-}
-
-impl std::fmt::Display for SelectedBlock {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{:?}", self.0))
-    }
-}
-
 pub fn spawn_player(mut commands: Commands) {
     let cam = commands
         .spawn((
             Camera3d::default(),
+            DistanceFog::default(),
             Transform::from_translation(Vec3::new(0., 1.75, 0.)),
         ))
         .id();
@@ -117,6 +135,9 @@ pub fn spawn_player(mut commands: Commands) {
         .spawn((
             RigidBody::Static,
             Player,
+            Spawning,
+            Frozen,
+            VoxelCollider::new(Vec3::new(0.3, 0.9, 0.3)),
             ExternalImpulse::default(),
             LockedAxes::ROTATION_LOCKED,
             Transform::from_translation(Vec3::new(0., GROUND_HEIGHT as f32, 0.)),
@@ -134,11 +155,106 @@ pub fn spawn_player(mut commands: Commands) {
         .add_child(cam);
 }
 
+/// Added to players while they wait for the ground at the [`SpawnPoint`] to be generated
+#[derive(Component)]
+pub struct Spawning;
+
+/// the column players spawn around
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct SpawnPoint(pub IVec2);
+
+/// returns the feet position on the closest safe surface to center
+/// searches outwards in rings up to radius columns away
+/// surface_height gives the top solid block of a column or none if it can't be stood on, see [`Map::surface_height`]
+pub fn find_safe_spawn(
+    surface_height: impl Fn(IVec2) -> Option<i32>,
+    center: IVec2,
+    radius: i32,
+) -> Option<Vec3> {
+    for ring in 0..=radius {
+        for z in -ring..=ring {
+            for x in -ring..=ring {
+                // only check the edge of the ring since the inside has already been checked
+                if x.abs() != ring && z.abs() != ring {
+                    continue;
+                }
+                let column = center + IVec2::new(x, z);
+                if let Some(height) = surface_height(column) {
+                    return Some(Vec3::new(
+                        column.x as f32,
+                        height as f32 + 0.5,
+                        column.y as f32,
+                    ));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// moves spawning players onto the surface once the column they spawn in has been generated
+fn place_spawning_players(
+    mut commands: Commands,
+    mut players: Query<(Entity, &mut Transform, &mut Velocity), With<Spawning>>,
+    map: Res<Map>,
+    spawn: Res<SpawnPoint>,
+) {
+    for (player, mut transform, mut velocity) in &mut players {
+        // keep the player over the spawn so the chunks around it get loaded
+        transform.translation.x = spawn.x as f32;
+        transform.translation.z = spawn.y as f32;
+        let surface_height = |column: IVec2| map.surface_height(column.x, column.y);
+        let Some(position) = find_safe_spawn(surface_height, spawn.0, CHUNK_SIZE) else {
+            continue;
+        };
+        transform.translation = position;
+        **velocity = Vec3::ZERO;
+        commands.entity(player).remove::<Spawning>();
+    }
+}
+
+/// lets physics move players again once the chunks around them have been meshed
+fn unfreeze_players(
+    mut commands: Commands,
+    players: Query<(Entity, &Transform), (With<Player>, With<Frozen>, Without<Spawning>)>,
+    map: Res<Map>,
+) {
+    for (player, transform) in &players {
+        let center: ChunkId = BlockId::from_translation(transform.translation).into();
+        let meshed = (-1..=1).all(|z| {
+            (-1..=1).all(|x| {
+                (0..5).all(|y| map.is_meshed(&ChunkId::new(center.x() + x, y, center.z() + z)))
+            })
+        });
+        if meshed {
+            commands.entity(player).remove::<Frozen>();
+        }
+    }
+}
+
+/// fades chunks into the sky before the edge of the view distance so they don't pop in
+fn update_fog(
+    view_distance: Res<ViewDistance>,
+    clear_color: Res<ClearColor>,
+    mut fogs: Query<&mut DistanceFog>,
+) {
+    let end = (view_distance.0 - 1) as f32 * CHUNK_SIZE as f32;
+    for mut fog in &mut fogs {
+        fog.color = clear_color.0;
+        fog.falloff = FogFalloff::Linear {
+            start: end * 0.6,
+            end,
+        };
+    }
+}
+
 /// Handles looking around if cursor is locked
 fn player_look(
     settings: Res<MovementSettings>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     mouse_motion: Res<AccumulatedMouseMotion>,
+    actions: Actions,
+    time: Res<Time>,
     mut query: Query<(&mut Transform, &PlayerCamera), With<Player>>,
     mut cams: Query<&mut Transform, (Without<Player>, With<Camera>)>,
 ) {
@@ -149,7 +265,8 @@ fn player_look(
                 continue;
             };
             let (_, mut pitch, _) = camera_transform.rotation.to_euler(EulerRot::YXZ);
-            if mouse_motion.delta.length_squared() < 0.1 {
+            let stick = actions.look(&settings) * settings.stick_look_speed * time.delta_secs();
+            if mouse_motion.delta.length_squared() < 0.1 && stick == Vec2::ZERO {
                 return;
             }
             let (mut yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
@@ -162,6 +279,8 @@ fn player_look(
                         (settings.sensitivity * mouse_motion.delta.y * window_scale).to_radians();
                     yaw -=
                         (settings.sensitivity * mouse_motion.delta.x * window_scale).to_radians();
+                    pitch += stick.y.to_radians();
+                    yaw -= stick.x.to_radians();
                 }
             }
             pitch = pitch.clamp(-1.57, 1.57);
@@ -175,70 +294,59 @@ fn player_look(
     }
 }
 
+/// how fast the player moves up when they jump
+const JUMP_SPEED: f32 = 5.;
+
 /// Handles keyboard input and movement
 fn player_move(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     time: Res<Time>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     settings: Res<MovementSettings>,
-    key_bindings: Res<KeyBindings>,
-    mut query: Query<(&mut Transform), With<Player>>,
-    map: Res<Map>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &VoxelCollider,
+            &PhysicsOutput,
+            &GameMode,
+        ),
+        (With<Player>, Without<Frozen>),
+    >,
+    voxels: VoxelQuery,
 ) {
     if let Ok(window) = primary_window.get_single() {
-        for (mut transform) in query.iter_mut() {
+        for (mut transform, mut physics_velocity, collider, output, mode) in query.iter_mut() {
+            if *mode == GameMode::Spectator {
+                continue;
+            }
             let mut velocity = Vec3::ZERO;
+            let mut jump = false;
             let local_z = transform.local_z();
             let forward = -Vec3::new(local_z.x, 0., local_z.z);
             let right = Vec3::new(local_z.z, 0., -local_z.x);
 
-            for key in keys.get_pressed() {
-                match window.cursor_options.grab_mode {
-                    CursorGrabMode::None => (),
-                    _ => {
-                        let key = *key;
-                        if key == key_bindings.move_forward {
-                            velocity += forward;
-                        } else if key == key_bindings.move_backward {
-                            velocity -= forward;
-                        } else if key == key_bindings.move_left {
-                            velocity -= right;
-                        } else if key == key_bindings.move_right {
-                            velocity += right;
-                        } else if key == key_bindings.move_ascend {
-                            velocity += Vec3::Y * 10.;
-                        } else if key == key_bindings.move_descend {
-                            velocity -= Vec3::Y * 10.;
-                        }
+            if window.cursor_options.grab_mode != CursorGrabMode::None {
+                let movement = actions.movement(&settings);
+                velocity += forward * movement.y + right * movement.x;
+                if actions.pressed(Action::MoveAscend) {
+                    if mode.can_fly() {
+                        velocity += Vec3::Y * 10.;
+                    } else {
+                        jump = true;
                     }
                 }
-            }
-
-            let next = transform.translation + velocity * time.delta_secs() * settings.speed;
-            if !map.get_block(BlockId::from_translation(next)).is_solid() {
-                transform.translation = next;
-            } else {
-                let end = transform.translation + velocity * settings.speed * time.delta_secs();
-                let hit = BlockId::from_translation(next).as_vec3();
-                let current = BlockId::from_translation(transform.translation).as_vec3();
-                let mut start = end;
-                let dif = hit - current;
-                if dif.x != 0. {
-                    start.x = transform.translation.x;
-                    let fin = start + (start - transform.translation);
-                    if !map.get_block(BlockId::from_translation(fin)).is_solid() {
-                        transform.translation = fin;
-                    }
+                if actions.pressed(Action::MoveDescend) && mode.can_fly() {
+                    velocity -= Vec3::Y * 10.;
                 }
+            }
 
-                if dif.z != 0. {
-                    start.z = transform.translation.z;
-                    let fin = start + (start - transform.translation);
-                    if !map.get_block(BlockId::from_translation(fin)).is_solid() {
-                        transform.translation = fin;
-                    }
-                }
-            };
+            if jump && output.grounded() {
+                physics_velocity.y = JUMP_SPEED;
+            }
+            let (min, max) = collider.bounds(transform.translation);
+            let sweep = voxels.sweep_aabb(min, max, velocity * time.delta_secs() * settings.speed);
+            transform.translation += sweep.delta;
         }
     } else {
         warn!("Primary window not found for `player_move`!");
@@ -247,101 +355,149 @@ fn player_move(
 
 /// Handles keyboard input and movement
 fn noclip_move(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     time: Res<Time>,
     primary_window: Query<&Window, With<PrimaryWindow>>,
     settings: Res<MovementSettings>,
-    key_bindings: Res<KeyBindings>,
-    mut query: Query<(&mut Transform), With<Player>>,
+    mut query: Query<(&mut Transform, &GameMode), With<Player>>,
 ) {
     if let Ok(window) = primary_window.get_single() {
-        for mut transform in query.iter_mut() {
+        for (mut transform, mode) in query.iter_mut() {
+            if *mode != GameMode::Spectator {
+                continue;
+            }
             let mut velocity = Vec3::ZERO;
             let local_z = transform.local_z();
             let forward = -Vec3::new(local_z.x, 0., local_z.z);
             let right = Vec3::new(local_z.z, 0., -local_z.x);
 
-            for key in keys.get_pressed() {
-                match window.cursor_options.grab_mode {
-                    CursorGrabMode::None => (),
-                    _ => {
-                        let key = *key;
-                        if key == key_bindings.move_forward {
-                            velocity += forward;
-                        } else if key == key_bindings.move_backward {
-                            velocity -= forward;
-                        } else if key == key_bindings.move_left {
-                            velocity -= right;
-                        } else if key == key_bindings.move_right {
-                            velocity += right;
-                        } else if key == key_bindings.move_ascend {
-                            velocity += Vec3::Y;
-                        } else if key == key_bindings.move_descend {
-                            velocity -= Vec3::Y;
-                        }
-                    }
+            if window.cursor_options.grab_mode != CursorGrabMode::None {
+                let movement = actions.movement(&settings);
+                velocity += forward * movement.y + right * movement.x;
+                if actions.pressed(Action::MoveAscend) {
+                    velocity += Vec3::Y;
+                }
+                if actions.pressed(Action::MoveDescend) {
+                    velocity -= Vec3::Y;
                 }
-
-                transform.translation += velocity * time.delta_secs() * settings.speed
             }
+
+            transform.translation += velocity * time.delta_secs() * settings.speed;
         }
     } else {
         warn!("Primary window not found for `player_move`!");
     }
 }
 
+/// how far away the player can break and place blocks
+const REACH: f32 = 5.;
+
+/// frozen players, like dead ones waiting to respawn, can't break or place blocks
 fn player_laser(
-    click: Res<ButtonInput<MouseButton>>,
-    players: Query<&PlayerCamera, With<Player>>,
+    actions: Actions,
+    mut players: Query<
+        (
+            &PlayerCamera,
+            &Transform,
+            &VoxelCollider,
+            &GameMode,
+            &mut Inventory,
+            &mut BreakProgress,
+        ),
+        (With<Player>, Without<Frozen>),
+    >,
     cameras: Query<&GlobalTransform, With<Camera>>,
-    map: Res<Map>,
+    mut map: ResMut<Map>,
     mut gizmos: Gizmos,
-    selected: Res<SelectedBlock>,
-    mut error: Local<bool>,
-) {
-    for player in &players {
-        if !*error {
-            error!("add back raycast");
-            *error = true;
-        }
-    }
-}
-
-fn set_selected(
-    input: Res<ButtonInput<KeyCode>>,
-    mut mouse: EventReader<MouseWheel>,
-    mut selected: ResMut<SelectedBlock>,
+    time: Res<Time>,
 ) {
-    for key in input.get_just_pressed() {
-        match key {
-            KeyCode::Digit1 => selected.set(BlockType::Air),
-            KeyCode::Digit2 => selected.set(BlockType::Bedrock),
-            KeyCode::Digit3 => selected.set(BlockType::CoalOre),
-            KeyCode::Digit4 => selected.set(BlockType::Dirt),
-            KeyCode::Digit5 => selected.set(BlockType::Grass),
-            KeyCode::Digit6 => selected.set(BlockType::GoldOre),
-            KeyCode::Digit7 => selected.set(BlockType::IronOre),
-            KeyCode::Digit8 => selected.set(BlockType::Gravel),
-            KeyCode::Digit9 => selected.set(BlockType::Sand),
-            KeyCode::Digit0 => selected.set(BlockType::Stone),
-            _ => {}
+    for (player, transform, collider, mode, mut inventory, mut progress) in &mut players {
+        if *mode == GameMode::Spectator {
+            progress.reset();
+            continue;
         }
-    }
-    for mouse in mouse.read() {
-        if mouse.y >= 1. {
-            let mut next = selected.get() as usize + 1;
-            if next >= BlockType::COUNT {
-                next = 0;
+        let Ok(camera) = cameras.get(player.get()) else {
+            error!("Player has no camera;");
+            continue;
+        };
+        let Some(VoxelHit { block, face, .. }) = raycast(
+            |block| map.get_block(block).target_boxes(map.get_state(block)),
+            camera.translation(),
+            camera.forward().as_vec3(),
+            REACH,
+        ) else {
+            progress.reset();
+            continue;
+        };
+        gizmos.cuboid(
+            Transform::from_translation(block.to_vec3()).with_scale(Vec3::splat(1.01)),
+            Color::BLACK,
+        );
+        let target = map.get_block(block);
+        let broken = match mode {
+            GameMode::Creative => actions.just_pressed(Action::Break),
+            _ if actions.pressed(Action::Break) => progress.damage(
+                block,
+                face.unwrap_or(Direction::Up),
+                target.hardness(),
+                time.delta_secs(),
+            ),
+            _ => {
+                progress.reset();
+                false
+            }
+        };
+        if broken {
+            map.break_block(block);
+            if *mode == GameMode::Survival {
+                inventory.add(target, 1);
             }
-            selected.set(BlockType::from_repr(next).expect("next to be 0..BlockType::COUNT"));
-        } else if mouse.y <= -1. {
-            let mut next = selected.get() as usize;
-            if next == 0 {
-                next = BlockType::COUNT - 1;
-            } else {
-                next -= 1;
+        } else if actions.just_pressed(Action::PickBlock) && *mode == GameMode::Creative {
+            inventory.set_selected(Some(ItemStack {
+                block: target,
+                count: 1,
+            }));
+        } else if actions.just_pressed(Action::Place) {
+            // can't place a block into the block you are looking out of
+            let Some(face) = face else {
+                continue;
+            };
+            let place = block.get(face);
+            let (min, max) = collider.bounds(transform.translation);
+            let inside_player = (place.to_vec3() - (min + max) / 2.)
+                .abs()
+                .cmplt(collider.half_extents + 0.5)
+                .all();
+            // the block only leaves the inventory once it is in the map
+            if inside_player || !map.is_loaded(&place.into()) || map.get_block(place).is_solid() {
+                continue;
+            }
+            let Some(placed) = inventory.selected().map(|stack| stack.block) else {
+                continue;
+            };
+            let look = camera.forward().as_vec3();
+            let state = BlockState::placed(placed, look, face, map.get_block(place));
+            map.set_block_state(place, placed, state);
+            if *mode != GameMode::Creative {
+                inventory.take_selected();
             }
-            selected.set(BlockType::from_repr(next).expect("next to be 0..BlockType::COUNT"))
         }
     }
 }
+
+#[test]
+fn test_find_safe_spawn() {
+    let flat = |_: IVec2| Some(10);
+    assert_eq!(
+        find_safe_spawn(flat, IVec2::new(3, -2), 4),
+        Some(Vec3::new(3., 10.5, -2.))
+    );
+    // a lake over the spawn pushes it to the nearest dry column
+    let lake = |column: IVec2| (column.x >= 2).then_some(20);
+    assert_eq!(
+        find_safe_spawn(lake, IVec2::ZERO, 4),
+        Some(Vec3::new(2., 20.5, -2.))
+    );
+    assert_eq!(find_safe_spawn(lake, IVec2::ZERO, 1), None);
+    assert_eq!(find_safe_spawn(|_| None, IVec2::ZERO, 8), None);
+}
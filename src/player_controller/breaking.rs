@@ -0,0 +1,169 @@
+use bevy::prelude::*;
+
+use super::Player;
+use crate::{
+    prelude::*,
+    textures::{CrackTextures, CRACK_STAGES},
+};
+
+pub struct BreakingPlugin;
+
+impl Plugin for BreakingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_crack_overlay).add_systems(
+            PostUpdate,
+            (
+                update_crack_materials.run_if(resource_exists_and_changed::<CrackTextures>),
+                update_crack_overlay,
+            ),
+        );
+    }
+}
+
+/// How far a player is through breaking the block they are looking at
+#[derive(Component, Default)]
+pub struct BreakProgress {
+    target: Option<(BlockId, Direction)>,
+    /// 0..1 where 1 is broken
+    damage: f32,
+}
+
+impl BreakProgress {
+    /// adds delta seconds of damage to block returning true once it breaks
+    /// changing block starts again from nothing
+    pub fn damage(&mut self, block: BlockId, face: Direction, hardness: f32, delta: f32) -> bool {
+        if self.target.map(|(target, _)| target) != Some(block) {
+            self.damage = 0.;
+        }
+        self.target = Some((block, face));
+        // bedrock and the like can never be broken
+        if !hardness.is_finite() {
+            return false;
+        }
+        self.damage += if hardness <= 0. { 1. } else { delta / hardness };
+        if self.damage >= 1. {
+            self.reset();
+            return true;
+        }
+        false
+    }
+
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.damage = 0.;
+    }
+
+    /// returns the crack stage to show for the block being broken
+    pub fn stage(&self) -> Option<usize> {
+        if self.target.is_none() || self.damage <= 0. {
+            return None;
+        }
+        Some(((self.damage * CRACK_STAGES as f32) as usize).min(CRACK_STAGES - 1))
+    }
+}
+
+#[derive(Component)]
+struct CrackOverlay;
+
+#[derive(Resource)]
+struct CrackMaterials([Handle<StandardMaterial>; CRACK_STAGES]);
+
+/// the textures are filled in by [`update_crack_materials`] once the texture array is made
+fn spawn_crack_overlay(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let stages = std::array::from_fn(|_| {
+        materials.add(StandardMaterial {
+            alpha_mode: AlphaMode::Multiply,
+            unlit: true,
+            depth_bias: 10.,
+            ..Default::default()
+        })
+    });
+    commands.spawn((
+        CrackOverlay,
+        Mesh3d(meshes.add(Rectangle::new(1., 1.))),
+        MeshMaterial3d(stages[0].clone()),
+        Transform::default(),
+        Visibility::Hidden,
+        Name::new("Crack Overlay"),
+    ));
+    commands.insert_resource(CrackMaterials(stages));
+}
+
+/// points the crack materials at the textures again when a resource pack changes them
+fn update_crack_materials(
+    cracks: Res<CrackTextures>,
+    crack_materials: Res<CrackMaterials>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for (stage, handle) in crack_materials.0.iter().enumerate() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.base_color_texture = Some(cracks.get(stage));
+        }
+    }
+}
+
+/// moves the crack overlay onto the face of the block being broken
+fn update_crack_overlay(
+    players: Query<&BreakProgress, With<Player>>,
+    mut overlay: Query<
+        (
+            &mut Transform,
+            &mut Visibility,
+            &mut MeshMaterial3d<StandardMaterial>,
+        ),
+        With<CrackOverlay>,
+    >,
+    materials: Res<CrackMaterials>,
+) {
+    let Ok((mut transform, mut visibility, mut material)) = overlay.get_single_mut() else {
+        return;
+    };
+    let progress = players.iter().find_map(|progress| {
+        progress
+            .stage()
+            .zip(progress.target)
+            .map(|(stage, (block, face))| (stage, block, face))
+    });
+    let Some((stage, block, face)) = progress else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    let normal = (IVec3::ZERO + face).as_vec3();
+    // sit just off the face so it doesn't z-fight with the block
+    transform.translation = block.to_vec3() + normal * 0.501;
+    transform.rotation = Quat::from_rotation_arc(Vec3::Z, normal);
+    *visibility = Visibility::Visible;
+    if material.0 != materials.0[stage] {
+        material.0 = materials.0[stage].clone();
+    }
+}
+
+#[test]
+fn test_break_progress() {
+    let block = BlockId::new(1, 2, 3);
+    let mut progress = BreakProgress::default();
+    assert_eq!(progress.stage(), None);
+    assert!(!progress.damage(block, Direction::Up, 1., 0.25));
+    assert_eq!(progress.stage(), Some(2));
+    assert!(!progress.damage(block, Direction::Left, 1., 0.5));
+    assert_eq!(progress.stage(), Some(7));
+    // breaking resets it ready for the next block
+    assert!(progress.damage(block, Direction::Left, 1., 0.25));
+    assert_eq!(progress.stage(), None);
+
+    // looking at another block starts again
+    progress.damage(block, Direction::Up, 1., 0.5);
+    assert!(!progress.damage(BlockId::new(0, 0, 0), Direction::Up, 1., 0.5));
+    assert_eq!(progress.stage(), Some(5));
+
+    // no hardness breaks straight away and bedrock never does
+    assert!(progress.damage(block, Direction::Up, 0., 0.));
+    assert!(!progress.damage(block, Direction::Up, f32::INFINITY, 100.));
+    assert_eq!(progress.stage(), None);
+    progress.reset();
+    assert_eq!(progress.stage(), None);
+}
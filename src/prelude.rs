@@ -1,4 +1,4 @@
-pub use crate::blocks::BlockType;
+pub use crate::blocks::{BlockState, BlockType};
 pub const CHUNK_SIZE: i32 = 16;
 pub const CHUNK_AREA: i32 = CHUNK_SIZE * CHUNK_SIZE;
 pub const CHUNK_VOLUME: i32 = CHUNK_AREA * CHUNK_SIZE;
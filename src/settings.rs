@@ -1,10 +1,159 @@
+use std::path::PathBuf;
+
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{Action, Actions, InputMap},
+    cam::MovementSettings,
+    textures::SelectedPack,
+};
+
+/// Loads [`ViewDistance`], [`MovementSettings`], the [`InputMap`] and the [`SelectedPack`] from a ron file
+/// and writes them back whenever they change
+pub struct SettingsPlugin {
+    pub path: PathBuf,
+}
+
+impl Default for SettingsPlugin {
+    fn default() -> Self {
+        SettingsPlugin {
+            path: PathBuf::from("settings.ron"),
+        }
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = Settings::load(&self.path);
+        app.insert_resource(settings.view_distance)
+            .insert_resource(SavedViewDistance(settings.view_distance))
+            .insert_resource(settings.movement)
+            .insert_resource(settings.input)
+            .insert_resource(settings.resource_pack)
+            .insert_resource(SettingsPath(self.path.clone()))
+            .register_type::<ViewDistance>()
+            .add_systems(Update, change_view_distance)
+            .add_systems(
+                Last,
+                (
+                    remember_view_distance.run_if(resource_changed::<ViewDistance>),
+                    // skip the first frame, everything has only just been loaded
+                    save_settings.run_if(
+                        not(resource_added::<ViewDistance>).and(
+                            resource_changed::<ViewDistance>
+                                .or(resource_changed::<MovementSettings>)
+                                .or(resource_changed::<InputMap>)
+                                .or(resource_changed::<SelectedPack>),
+                        ),
+                    ),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Resource)]
+struct SettingsPath(PathBuf);
+
+/// the view distance to write to the file
+/// kept apart from [`ViewDistance`] so overrides like --view-distance are not saved when something else changes
+#[derive(Resource)]
+struct SavedViewDistance(ViewDistance);
+
+/// Everything that is saved in the settings file
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub view_distance: ViewDistance,
+    pub movement: MovementSettings,
+    pub input: InputMap,
+    /// the name of a pack in the resourcepacks directory
+    pub resource_pack: SelectedPack,
+}
+
+impl Settings {
+    /// loads the settings from path falling back to the defaults if it is missing or broken
+    pub fn load(path: &std::path::Path) -> Settings {
+        let file = match std::fs::read_to_string(path) {
+            Ok(file) => file,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    error!("Failed to read {}: {}", path.display(), e);
+                }
+                return Settings::default();
+            }
+        };
+        match Settings::from_ron(&file) {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Failed to parse {}: {}", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    /// parses and validates settings
+    pub fn from_ron(file: &str) -> Result<Settings, ron::error::SpannedError> {
+        let mut settings: Settings = ron::from_str(file)?;
+        settings.view_distance.validate();
+        settings.movement.validate();
+        settings.input = settings.input.with_defaults();
+        Ok(settings)
+    }
+
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+    }
+
+    pub fn save(&self, path: &std::path::Path) {
+        let file = match self.to_ron() {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to serialize settings: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = std::fs::write(path, file) {
+            error!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// the view distance is only saved once the player changes it, not when an override is inserted
+fn remember_view_distance(view_distance: Res<ViewDistance>, mut saved: ResMut<SavedViewDistance>) {
+    if !view_distance.is_added() {
+        saved.0 = *view_distance;
+    }
+}
+
+fn save_settings(
+    path: Res<SettingsPath>,
+    view_distance: Res<SavedViewDistance>,
+    movement: Res<MovementSettings>,
+    input: Res<InputMap>,
+    resource_pack: Res<SelectedPack>,
+) {
+    Settings {
+        view_distance: view_distance.0,
+        movement: movement.clone(),
+        input: input.clone(),
+        resource_pack: resource_pack.clone(),
+    }
+    .save(&path.0);
+}
 
-#[derive(Resource, Reflect, DerefMut, Deref)]
+#[derive(
+    Resource, Reflect, DerefMut, Deref, Debug, Clone, Copy, PartialEq, Serialize, Deserialize,
+)]
 #[reflect(Resource)]
+#[serde(transparent)]
 pub struct ViewDistance(pub i32);
 
 impl ViewDistance {
+    pub const MIN: i32 = 3;
+    pub const MAX: i32 = 25;
+
     /// takes 0..1 where 0 is 5 and 1 is 25
     pub fn set(&mut self, val: f32) {
         let val = val.clamp(0., 1.);
@@ -14,6 +163,19 @@ impl ViewDistance {
     pub fn get(&self) -> f32 {
         (self.0 - 5) as f32 / 20.
     }
+
+    /// clamps to [`ViewDistance::MIN`]..=[`ViewDistance::MAX`]
+    pub fn validate(&mut self) {
+        if !(Self::MIN..=Self::MAX).contains(&self.0) {
+            warn!(
+                "view_distance must be in {}..={}, got {}",
+                Self::MIN,
+                Self::MAX,
+                self.0
+            );
+            self.0 = self.0.clamp(Self::MIN, Self::MAX);
+        }
+    }
 }
 
 impl Default for ViewDistance {
@@ -22,15 +184,65 @@ impl Default for ViewDistance {
     }
 }
 
-pub fn change_view_distance(input: Res<ButtonInput<KeyCode>>, mut view: ResMut<ViewDistance>) {
-    if input.just_pressed(KeyCode::NumpadAdd) {
+pub fn change_view_distance(actions: Actions, mut view: ResMut<ViewDistance>) {
+    if actions.just_pressed(Action::ViewDistanceUp) && view.0 < ViewDistance::MAX {
         view.0 += 1;
         println!("view: {}", view.0);
     }
-    if input.just_pressed(KeyCode::NumpadSubtract) {
-        if view.0 > 3 {
-            view.0 -= 1;
-        }
+    if actions.just_pressed(Action::ViewDistanceDown) && view.0 > ViewDistance::MIN {
+        view.0 -= 1;
         println!("view: {}", view.0);
     }
 }
+
+#[test]
+fn test_settings_round_trip() {
+    let mut settings = Settings {
+        view_distance: ViewDistance(12),
+        ..Default::default()
+    };
+    settings.movement.speed = 20.;
+    settings.resource_pack = SelectedPack(Some("PureBDcraft.zip".to_string()));
+    settings.input.clear(Action::ToggleDebug).bind(
+        Action::ToggleDebug,
+        crate::actions::Binding::Key(KeyCode::F4),
+    );
+    let file = settings.to_ron().unwrap();
+    assert_eq!(Settings::from_ron(&file).unwrap(), settings);
+
+    // out of range values are clamped and missing ones use the defaults
+    let loaded = Settings::from_ron("(view_distance: 100, movement: (speed: -1.))").unwrap();
+    assert_eq!(loaded.view_distance, ViewDistance(ViewDistance::MAX));
+    assert_eq!(loaded.movement, MovementSettings::default());
+    assert_eq!(loaded.input, InputMap::default());
+}
+
+#[test]
+fn test_view_distance_override_not_saved() {
+    let path = std::env::temp_dir().join(format!("settings_test_{}.ron", std::process::id()));
+    Settings {
+        view_distance: ViewDistance(8),
+        ..Default::default()
+    }
+    .save(&path);
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        bevy::input::InputPlugin,
+        SettingsPlugin { path: path.clone() },
+    ))
+    // the same as --view-distance
+    .insert_resource(ViewDistance(20));
+    app.update();
+    app.world_mut().resource_mut::<MovementSettings>().speed = 20.;
+    app.update();
+    let saved = Settings::load(&path);
+    assert_eq!(saved.movement.speed, 20.);
+    assert_eq!(saved.view_distance, ViewDistance(8));
+    // changing it in game is saved
+    app.world_mut().resource_mut::<ViewDistance>().0 = 10;
+    app.update();
+    let saved = Settings::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(saved.view_distance, ViewDistance(10));
+}
@@ -2,17 +2,26 @@ use std::sync::{Arc, RwLock, RwLockReadGuard};
 
 use bevy::{
     prelude::*,
+    render::primitives::Aabb,
     tasks::Task,
     utils::{HashMap, HashSet},
 };
 use noise::NoiseFn;
 use rand::{Rng, SeedableRng};
+use strum::IntoEnumIterator;
 
-use crate::{player_controller::Player, prelude::*, settings::ViewDistance};
+use crate::{
+    blocks::BlockModel,
+    player_controller::{Player, SpawnPoint},
+    prelude::*,
+    settings::ViewDistance,
+    GameState,
+};
 
 use bevy::prelude::*;
 
 mod chunk;
+pub mod export;
 
 use chunk::*;
 
@@ -20,12 +29,21 @@ pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Seed(3))
+        app.init_resource::<Seed>()
             .add_systems(Update, (que_chunks, spawn_visable_chunks))
+            .add_systems(
+                Update,
+                remesh_all.run_if(on_event::<crate::textures::BlockTexturesChanged>),
+            )
             .add_systems(PreUpdate, (start_gen_chunks, start_mesh_chunks))
             .add_systems(PostUpdate, (finish_gen_chunks, finish_mesh_chunks))
             .add_systems(First, update_can_mesh)
+            .add_systems(
+                Update,
+                update_loading_progress.run_if(in_state(GameState::GenWorld)),
+            )
             .init_resource::<MapDescriptor>()
+            .init_resource::<LoadingProgress>()
             .init_resource::<Map>();
     }
 }
@@ -38,7 +56,15 @@ struct MapDescriptor {
 
 impl FromWorld for MapDescriptor {
     fn from_world(world: &mut World) -> Self {
-        let seed = world.get_resource::<Seed>().unwrap_or(&Seed(3)).0;
+        let seed = world
+            .get_resource::<Seed>()
+            .map_or(Seed::default().0, |seed| seed.0);
+        MapDescriptor::new(seed)
+    }
+}
+
+impl MapDescriptor {
+    fn new(seed: u64) -> Self {
         let mut noise = noise::Fbm::<noise::OpenSimplex>::new(((seed >> 32) ^ seed) as u32);
         let rng = rand::rngs::StdRng::seed_from_u64(seed);
         noise.frequency = 0.2;
@@ -51,6 +77,43 @@ impl FromWorld for MapDescriptor {
 #[derive(Resource, Deref)]
 pub struct Seed(u64);
 
+impl Seed {
+    pub fn new(seed: u64) -> Seed {
+        Seed(seed)
+    }
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Seed(3)
+    }
+}
+
+/// how many chunks around the spawn point need to be meshed before the game starts
+const PRELOAD_RADIUS: i32 = 2;
+
+/// How much of the area around spawn has been loaded while in [`GameState::GenWorld`]
+#[derive(Resource, Default, Debug)]
+pub struct LoadingProgress {
+    pub generated: usize,
+    pub meshed: usize,
+    pub total: usize,
+}
+
+impl LoadingProgress {
+    /// returns 0..1 where 1 is fully loaded
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.;
+        }
+        (self.generated + self.meshed) as f32 / (self.total * 2) as f32
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.total != 0 && self.meshed == self.total
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct Map {
     pub generate_tasks: HashMap<ChunkId, Task<Result<Chunk, GenError>>>,
@@ -61,6 +124,10 @@ pub struct Map {
     pub to_gen: HashSet<ChunkId>,
     pub to_mesh: HashMap<ChunkId, Entity>,
     pub id_to_entity: HashMap<ChunkId, Entity>,
+    /// chunks whose last mesh task finished without an error
+    pub meshed: HashSet<ChunkId>,
+    /// blocks that have been changed with [`Map::set_block`] since they were last checked
+    pub changed_blocks: Vec<BlockId>,
 }
 
 impl Map {
@@ -129,6 +196,12 @@ impl Map {
         self.chunk_data.write().unwrap().remove(id);
         self.has_data.remove(id);
         self.id_to_entity.remove(id);
+        self.meshed.remove(id);
+    }
+
+    /// true if the chunk has been generated and is in memory
+    pub fn is_loaded(&self, id: &ChunkId) -> bool {
+        self.has_data.contains(id)
     }
 
     pub fn get_block(&self, mut block: BlockId) -> BlockType {
@@ -140,6 +213,108 @@ impl Map {
             .get(&chunk)
             .get_block(block.x, block.y, block.z)
     }
+
+    /// returns true once the chunk has been meshed successfully and is not waiting to be remeshed
+    pub fn is_meshed(&self, id: &ChunkId) -> bool {
+        let Some(entity) = self.id_to_entity.get(id) else {
+            return false;
+        };
+        self.meshed.contains(id)
+            && !self.mesh_task.contains_key(entity)
+            && !self.to_mesh.contains_key(id)
+    }
+
+    /// returns the height of the highest solid block in the column, plants are skipped
+    /// returns None if the column has not been generated or the top of it is under water
+    pub fn surface_height(&self, x: i32, z: i32) -> Option<i32> {
+        for y in 0..5 {
+            let chunk: ChunkId = BlockId::new(x, y * CHUNK_SIZE, z).into();
+            if !self.has_data.contains(&chunk) {
+                return None;
+            }
+        }
+        for y in (0..5 * CHUNK_SIZE).rev() {
+            let block = self.get_block(BlockId::new(x, y, z));
+            if block.is_solid() {
+                return Some(y);
+            }
+            if block.properties().model == BlockModel::Liquid {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// the state bits of the block, the default if the chunk has not been generated
+    pub fn get_state(&self, block: BlockId) -> BlockState {
+        let chunk: ChunkId = block.into();
+        let block = block.as_local();
+        self.chunk_data
+            .read()
+            .unwrap()
+            .get(&chunk)
+            .get_state(block.x, block.y, block.z)
+    }
+
+    /// sets the block returning what was there before
+    /// the chunk and any neighbor touching the block are qued to be remeshed
+    /// does nothing if the chunk has not been generated yet
+    pub fn set_block(&mut self, block: BlockId, to: BlockType) -> BlockType {
+        self.set_block_state(block, to, BlockState::DEFAULT)
+    }
+
+    /// replaces the block with air returning what was there
+    /// the water in a waterlogged block is left behind
+    pub fn break_block(&mut self, block: BlockId) -> BlockType {
+        let left = if self.get_state(block).waterlogged() {
+            BlockType::Water
+        } else {
+            BlockType::Air
+        };
+        self.set_block(block, left)
+    }
+
+    /// like [`Map::set_block`] but with the state the block is placed in
+    pub fn set_block_state(
+        &mut self,
+        block: BlockId,
+        to: BlockType,
+        state: BlockState,
+    ) -> BlockType {
+        let chunk: ChunkId = block.into();
+        let local = block.as_local();
+        let (old, old_state) = {
+            let mut data = self.chunk_data.write().unwrap();
+            let Some(data) = data.get_mut(&chunk) else {
+                return BlockType::Air;
+            };
+            data.set_block_state(local.x, local.y, local.z, to, state)
+        };
+        if old == to && old_state == state {
+            return old;
+        }
+        self.changed_blocks.push(block);
+        self.remesh(chunk);
+        for direction in Direction::iter() {
+            let neighbor: ChunkId = block.get(direction).into();
+            if neighbor != chunk {
+                self.remesh(neighbor);
+            }
+        }
+        old
+    }
+
+    fn remesh(&mut self, id: ChunkId) {
+        if let Some(entity) = self.get_entity(&id) {
+            self.to_mesh.insert(id, entity);
+        }
+    }
+
+    /// ques every chunk that has a mesh to be meshed again
+    fn remesh_all(&mut self) {
+        let chunks = self.id_to_entity.clone();
+        self.to_mesh.extend(chunks);
+    }
 }
 
 #[derive(Default)]
@@ -156,6 +331,9 @@ impl ChunkData {
     fn try_get(&self, id: &ChunkId) -> Option<&Chunk> {
         self.0.get(id)
     }
+    fn get_mut(&mut self, id: &ChunkId) -> Option<&mut Chunk> {
+        self.0.get_mut(id)
+    }
     fn set(&mut self, id: ChunkId, data: Chunk) {
         if id.y < 0 || id.y > 4 {
             panic!()
@@ -239,6 +417,7 @@ fn finish_mesh_chunks(
     mut commands: Commands,
     mut map: ResMut<Map>,
     asset_server: Res<AssetServer>,
+    chunks: Query<&ChunkId>,
 ) {
     let finished = map
         .mesh_task
@@ -248,21 +427,75 @@ fn finish_mesh_chunks(
         .collect::<Vec<_>>();
     for id in finished {
         let task = map.mesh_task.remove(&id).unwrap();
-        let mesh = match futures_lite::future::block_on(task.cancel()).expect("Is finished") {
-            Ok(chunk) => chunk,
+        let result = futures_lite::future::block_on(task.cancel()).expect("Is finished");
+        let Ok(chunk) = chunks.get(id) else {
+            // the chunk was despawned while it was being meshed
+            continue;
+        };
+        let mesh = match result {
+            Ok(mesh) => mesh,
             Err(e) => {
                 error!("{}", e);
+                map.meshed.remove(chunk);
                 continue;
             }
         };
+        map.meshed.insert(*chunk);
         commands.entity(id).insert(Mesh3d(asset_server.add(mesh)));
     }
 }
 
+fn remesh_all(mut map: ResMut<Map>) {
+    map.remesh_all();
+}
+
 fn update_can_mesh(mut map: ResMut<Map>) {
     map.update_can_mesh();
 }
 
+fn update_loading_progress(
+    map: Res<Map>,
+    spawn: Res<SpawnPoint>,
+    view_distance: Res<ViewDistance>,
+    mut progress: ResMut<LoadingProgress>,
+    mut next: ResMut<NextState<GameState>>,
+) {
+    let center: ChunkId = BlockId::new(spawn.x, 0, spawn.y).into();
+    // chunks are only spawned within the view distance so don't wait on any past it
+    let radius = PRELOAD_RADIUS.min(view_distance.0 - 1);
+    let mut generated = 0;
+    let mut meshed = 0;
+    let mut total = 0;
+    for z in -radius..=radius {
+        for x in -radius..=radius {
+            for y in 0..5 {
+                let id = ChunkId::new(center.x() + x, y, center.z() + z);
+                total += 1;
+                if map.has_data.contains(&id) {
+                    generated += 1;
+                }
+                if map.is_meshed(&id) {
+                    meshed += 1;
+                }
+            }
+        }
+    }
+    if progress.meshed != meshed || progress.generated != generated {
+        info!(
+            "Loading world: {}/{} generated, {}/{} meshed",
+            generated, total, meshed, total
+        );
+    }
+    *progress = LoadingProgress {
+        generated,
+        meshed,
+        total,
+    };
+    if progress.is_done() {
+        next.set(GameState::Playing);
+    }
+}
+
 fn spawn_visable_chunks(
     mut commands: Commands,
     player: Query<&Transform, With<Player>>,
@@ -294,6 +527,8 @@ fn spawn_visable_chunks(
                         )),
                         MeshMaterial3d(matt.get_atlas()),
                         Mesh3d(Handle::weak_from_u128(pos.to_u128())),
+                        // packed vertices have no positions for bevy to work the bounds out from
+                        Aabb::from_min_max(Vec3::splat(-0.5), Vec3::splat(CHUNK_SIZE as f32 - 0.5)),
                         pos,
                     ))
                     .id();
@@ -301,3 +536,34 @@ fn spawn_visable_chunks(
         }
     }
 }
+
+#[test]
+fn test_is_meshed() {
+    let mut map = Map::default();
+    let id = ChunkId::new(0, 0, 0);
+    let entity = Entity::from_raw(1);
+    assert!(!map.is_meshed(&id));
+    // a chunk whose mesh task failed has an entity and no task but isn't meshed
+    map.id_to_entity.insert(id, entity);
+    assert!(!map.is_meshed(&id));
+    map.meshed.insert(id);
+    assert!(map.is_meshed(&id));
+    map.to_mesh.insert(id, entity);
+    assert!(!map.is_meshed(&id));
+}
+
+#[test]
+fn test_break_block() {
+    let mut map = Map::default();
+    map.add_chunk(ChunkId::new(0, 0, 0), Chunk::EMPTY);
+    let slab = BlockId::new(1, 2, 3);
+    let wet = BlockState::DEFAULT.with_waterlogged(true);
+    map.set_block_state(slab, BlockType::StoneSlab, wet);
+    assert_eq!(map.break_block(slab), BlockType::StoneSlab);
+    assert_eq!(map.get_block(slab), BlockType::Water);
+    assert!(!map.get_state(slab).waterlogged());
+    let stone = BlockId::new(1, 3, 3);
+    map.set_block(stone, BlockType::Stone);
+    assert_eq!(map.break_block(stone), BlockType::Stone);
+    assert_eq!(map.get_block(stone), BlockType::Air);
+}
@@ -1,7 +1,11 @@
 use std::sync::{Arc, RwLock};
 
-use super::{BlockType, ChunkData};
-use crate::prelude::*;
+use super::{BlockState, BlockType, ChunkData};
+use crate::{
+    blocks::FaceMask,
+    prelude::*,
+    textures::{ChunkVertex, ATTRIBUTE_VOXEL},
+};
 use bevy::{asset::RenderAssetUsages, prelude::*, render::mesh::Indices, utils::HashMap};
 use noise::NoiseFn;
 use rand::Rng;
@@ -10,11 +14,14 @@ use strum::IntoEnumIterator;
 #[derive(Clone)]
 pub struct Chunk {
     blocks: [BlockType; CHUNK_VOLUME as usize],
+    /// the state bits of each block, reset when the block is replaced
+    states: [BlockState; CHUNK_VOLUME as usize],
 }
 
 impl Chunk {
     pub const EMPTY: Chunk = Chunk {
         blocks: [BlockType::Air; CHUNK_VOLUME as usize],
+        states: [BlockState::DEFAULT; CHUNK_VOLUME as usize],
     };
 
     pub async fn new<T: NoiseFn<f64, 2>>(
@@ -52,7 +59,10 @@ impl Chunk {
                 }
             }
         }
-        Ok(Chunk { blocks: chunk })
+        Ok(Chunk {
+            blocks: chunk,
+            ..Chunk::EMPTY
+        })
     }
 
     pub async fn gen_mesh(
@@ -60,57 +70,84 @@ impl Chunk {
         data: Arc<RwLock<ChunkData>>,
         atlas: TextureHandles,
     ) -> Result<Mesh, MeshError> {
-        let mut positions = Vec::new();
-        let mut uvs = Vec::new();
+        let mut vertices = Vec::new();
         let mut indices = Vec::new();
-        let mut color: Vec<[f32; 4]> = Vec::new();
-        let chunk = data
-            .read()
-            .unwrap()
-            .try_get(&id)
-            .ok_or(MeshError::ChunkNotGenerated(id))?
-            .clone();
+        let blocks = Neighborhood::new(id, &data.read().unwrap())?;
         for y in 0..CHUNK_SIZE {
             for z in 0..CHUNK_SIZE {
                 for x in 0..CHUNK_SIZE {
-                    let block = chunk.get_block(x, y, z);
+                    let pos = IVec3::new(x, y, z);
+                    let block = blocks.get(pos);
                     if let BlockType::Air = block {
                         continue;
                     }
+                    let state = blocks.get_state(pos);
+                    let mut neighbors = [(BlockType::Air, BlockState::DEFAULT); 6];
                     for direction in Direction::iter() {
-                        let mut neighbor = IVec3::new(x, y, z) + direction;
-                        let neighbor = if in_chunk(neighbor) {
-                            chunk.blocks[Chunk::index(neighbor.x, neighbor.y, neighbor.z)]
+                        let neighbor = pos + direction;
+                        neighbors[direction as usize] =
+                            (blocks.get(neighbor), blocks.get_state(neighbor));
+                    }
+                    // water hides its faces against the water in waterlogged blocks
+                    let wet_neighbors = neighbors.map(|(neighbor, state)| {
+                        if state.waterlogged() {
+                            (BlockType::Water, BlockState::DEFAULT)
                         } else {
-                            if neighbor.y < 0 {
-                                neighbor.y += CHUNK_SIZE
-                            } else if neighbor.x < 0 {
-                                neighbor.x += CHUNK_SIZE
-                            } else if neighbor.z < 0 {
-                                neighbor.z += CHUNK_SIZE
-                            }
-                            let id = id.0 + direction;
-                            data.read().unwrap().get(&ChunkId(id)).blocks[Chunk::index(
-                                neighbor.x % CHUNK_SIZE,
-                                neighbor.y % CHUNK_SIZE,
-                                neighbor.z % CHUNK_SIZE,
-                            )]
-                        };
-
-                        if neighbor.is_transparent() {
-                            let block = block.gen_mesh(direction, &atlas);
-                            indices.extend(
-                                block
-                                    .indices
-                                    .into_iter()
-                                    .map(|i| *i + positions.len() as u32),
-                            );
-                            positions.extend(block.pos.into_iter().map(|pos| {
-                                [pos[0] + x as f32, pos[1] + y as f32, pos[2] + z as f32]
-                            }));
-                            uvs.extend(block.uv.into_iter());
-                            color.extend(block.color.into_iter());
+                            (neighbor, state)
                         }
+                    });
+                    let mut faces = if block == BlockType::Water {
+                        block.gen_mesh(state, &wet_neighbors, &atlas)
+                    } else {
+                        block.gen_mesh(state, &neighbors, &atlas)
+                    };
+                    if state.waterlogged() {
+                        faces.extend(BlockType::Water.gen_mesh(
+                            BlockState::DEFAULT,
+                            &wet_neighbors,
+                            &atlas,
+                        ));
+                    }
+                    for face in faces {
+                        let ao = face
+                            .pos
+                            .iter()
+                            .map(|corner| {
+                                if face.shaded {
+                                    blocks.ambient_occlusion(pos, face.face, *corner)
+                                } else {
+                                    0
+                                }
+                            })
+                            .collect::<Vec<_>>();
+                        // split each quad along its darker diagonal so the shading is symmetric
+                        let flip = ao
+                            .chunks_exact(4)
+                            .map(|ao| ao[0] + ao[2] > ao[1] + ao[3])
+                            .collect::<Vec<_>>();
+                        indices.extend(face.indices.iter().map(|i| {
+                            let quad = *i / 4;
+                            let i = if flip[quad as usize] {
+                                quad * 4 + (*i + 1) % 4
+                            } else {
+                                *i
+                            };
+                            i + vertices.len() as u32
+                        }));
+                        vertices.extend(face.pos.iter().zip(face.uv).zip(ao).map(
+                            |((corner, uv), ao)| {
+                                ChunkVertex {
+                                    position: Vec3::from_array(*corner) + pos.as_vec3(),
+                                    face: face.face,
+                                    uv: Vec2::from_array(uv),
+                                    ao,
+                                    light: block.properties().light,
+                                    tint: face.tint,
+                                    layer: face.layer,
+                                }
+                                .pack()
+                            },
+                        ));
                     }
                 }
             }
@@ -120,10 +157,7 @@ impl Chunk {
             RenderAssetUsages::all(),
         );
         mesh.insert_indices(Indices::U32(indices));
-        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 1., 0.]; positions.len()]);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, color);
-        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(ATTRIBUTE_VOXEL, vertices);
         Ok(mesh)
     }
 
@@ -141,6 +175,34 @@ impl Chunk {
             .copied()
             .unwrap_or(BlockType::Air)
     }
+
+    pub fn get_state(&self, x: i32, y: i32, z: i32) -> BlockState {
+        self.states
+            .get(Chunk::index(x % CHUNK_SIZE, y % CHUNK_SIZE, z % CHUNK_SIZE))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// replaces the block and resets its state
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: BlockType) -> BlockType {
+        self.set_block_state(x, y, z, block, BlockState::DEFAULT).0
+    }
+
+    /// returns the block and state that were there before
+    pub fn set_block_state(
+        &mut self,
+        x: i32,
+        y: i32,
+        z: i32,
+        block: BlockType,
+        state: BlockState,
+    ) -> (BlockType, BlockState) {
+        let index = Chunk::index(x, y, z);
+        (
+            std::mem::replace(&mut self.blocks[index], block),
+            std::mem::replace(&mut self.states[index], state),
+        )
+    }
 }
 
 #[inline(always)]
@@ -153,6 +215,92 @@ fn in_chunk(pos: IVec3) -> bool {
         && pos.z >= 0
 }
 
+/// A chunk and the blocks one past each of its sides
+/// copied out together so the mesher only takes the lock once
+struct Neighborhood {
+    blocks: Vec<BlockType>,
+    states: Vec<BlockState>,
+}
+
+impl Neighborhood {
+    const SIZE: i32 = CHUNK_SIZE + 2;
+
+    fn new(id: ChunkId, data: &ChunkData) -> Result<Neighborhood, MeshError> {
+        let center = data.try_get(&id).ok_or(MeshError::ChunkNotGenerated(id))?;
+        let mut blocks = Vec::with_capacity((Self::SIZE * Self::SIZE * Self::SIZE) as usize);
+        let mut states = Vec::with_capacity(blocks.capacity());
+        for y in -1..=CHUNK_SIZE {
+            for z in -1..=CHUNK_SIZE {
+                for x in -1..=CHUNK_SIZE {
+                    let pos = IVec3::new(x, y, z);
+                    let chunk = if in_chunk(pos) {
+                        center
+                    } else {
+                        data.get(&ChunkId(id.0 + pos.div_euclid(IVec3::splat(CHUNK_SIZE))))
+                    };
+                    let local = pos.rem_euclid(IVec3::splat(CHUNK_SIZE));
+                    let index = Chunk::index(local.x, local.y, local.z);
+                    blocks.push(chunk.blocks[index]);
+                    states.push(chunk.states[index]);
+                }
+            }
+        }
+        Ok(Neighborhood { blocks, states })
+    }
+
+    fn index(pos: IVec3) -> usize {
+        let pos = pos + 1;
+        (pos.x + pos.z * Self::SIZE + pos.y * Self::SIZE * Self::SIZE) as usize
+    }
+
+    /// pos is relative to the chunk and can be one block outside it
+    fn get(&self, pos: IVec3) -> BlockType {
+        self.blocks[Self::index(pos)]
+    }
+
+    fn get_state(&self, pos: IVec3) -> BlockState {
+        self.states[Self::index(pos)]
+    }
+
+    /// how many of the three blocks touching a corner of a face are in the way of light 0..=3
+    fn ambient_occlusion(&self, pos: IVec3, direction: Direction, corner: [f32; 3]) -> u8 {
+        let normal = IVec3::ZERO + direction;
+        let front = pos + normal;
+        // step from the middle of the side of the block towards the corner along the two axes of the face
+        // corners in line with the middle like the top of a slab's side don't step that way
+        let step = |offset: f32| {
+            if offset.abs() < 0.01 {
+                0
+            } else {
+                offset.signum() as i32
+            }
+        };
+        let mut sides = [IVec3::ZERO; 2];
+        let mut i = 0;
+        for axis in 0..3 {
+            if normal[axis] == 0 && i < 2 {
+                sides[i][axis] = step(corner[axis]);
+                i += 1;
+            }
+        }
+        // only blocks whose side facing the face is filled in shade it, like hiding faces
+        let blocked = |pos: IVec3| {
+            let block = self.get(pos);
+            !block.is_transparent()
+                && block
+                    .state_shape(self.get_state(pos))
+                    .side(direction.rev())
+                    .covers(&FaceMask::FULL)
+        };
+        let (a, b) = (blocked(front + sides[0]), blocked(front + sides[1]));
+        if a && b {
+            3
+        } else {
+            a as u8 + b as u8 + blocked(front + sides[0] + sides[1]) as u8
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum GenError {}
 
@@ -0,0 +1,228 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use bevy::{
+    prelude::*,
+    render::mesh::{Indices, VertexAttributeValues},
+};
+use futures_lite::future::block_on;
+
+use super::{Chunk, ChunkData, GenError, MapDescriptor, MeshError};
+use crate::{
+    blocks::BlockRegistry,
+    cli::ExportArgs,
+    prelude::*,
+    textures::{face_color, ChunkVertex, ATTRIBUTE_VOXEL},
+};
+
+/// how many chunks tall the world is
+const WORLD_CHUNKS: i32 = 5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Image(#[from] image::ImageError),
+    #[error("{0}")]
+    Gen(#[from] GenError),
+    #[error("{0}")]
+    Mesh(#[from] MeshError),
+}
+
+/// A square of generated chunk columns
+pub struct Region {
+    data: Arc<RwLock<ChunkData>>,
+    /// smallest chunk x and z
+    min: IVec2,
+    /// how many chunk columns along each side
+    size: i32,
+}
+
+impl Region {
+    /// generates every chunk column within radius of center the same way the game does
+    pub fn generate(seed: u64, center: IVec2, radius: i32) -> Result<Region, GenError> {
+        let descriptor = MapDescriptor::new(seed);
+        let mut data = ChunkData::default();
+        let min = center - IVec2::splat(radius);
+        let size = radius * 2 + 1;
+        for x in 0..size {
+            for z in 0..size {
+                for y in 0..WORLD_CHUNKS {
+                    let id = ChunkId::new(min.x + x, y, min.y + z);
+                    let chunk = block_on(Chunk::new(
+                        descriptor.noise.clone(),
+                        id.0,
+                        descriptor.rng.clone(),
+                    ))?;
+                    data.set(id, chunk);
+                }
+            }
+        }
+        Ok(Region {
+            data: Arc::new(RwLock::new(data)),
+            min,
+            size,
+        })
+    }
+
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkId> + '_ {
+        (0..self.size).flat_map(move |x| {
+            (0..self.size).flat_map(move |z| {
+                (0..WORLD_CHUNKS).map(move |y| ChunkId::new(self.min.x + x, y, self.min.y + z))
+            })
+        })
+    }
+
+    /// counts how many of each block are in a chunk
+    pub fn histogram(&self, id: ChunkId) -> Vec<(BlockType, usize)> {
+        let data = self.data.read().unwrap();
+        let chunk = data.get(&id);
+        let mut counts = vec![0; BlockRegistry::global().len()];
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    counts[chunk.get_block(x, y, z).id() as usize] += 1;
+                }
+            }
+        }
+        BlockType::iter().zip(counts).collect()
+    }
+
+    /// one pixel per column coloured by the top block and shaded by its height
+    pub fn top_down_image(&self) -> image::RgbImage {
+        let data = self.data.read().unwrap();
+        let width = (self.size * CHUNK_SIZE) as u32;
+        let colors = BlockType::iter()
+            .map(|block| face_color(block, Direction::Up))
+            .collect::<Vec<_>>();
+        image::RgbImage::from_fn(width, width, |x, z| {
+            let world_x = self.min.x * CHUNK_SIZE + x as i32;
+            let world_z = self.min.y * CHUNK_SIZE + z as i32;
+            let (chunk_x, local_x) = (
+                world_x.div_euclid(CHUNK_SIZE),
+                world_x.rem_euclid(CHUNK_SIZE),
+            );
+            let (chunk_z, local_z) = (
+                world_z.div_euclid(CHUNK_SIZE),
+                world_z.rem_euclid(CHUNK_SIZE),
+            );
+            for y in (0..WORLD_CHUNKS * CHUNK_SIZE).rev() {
+                let chunk = data.get(&ChunkId::new(chunk_x, y / CHUNK_SIZE, chunk_z));
+                let block = chunk.get_block(local_x, y % CHUNK_SIZE, local_z);
+                if block == BlockType::Air {
+                    continue;
+                }
+                let shade = 0.5 + 0.5 * y as f32 / (WORLD_CHUNKS * CHUNK_SIZE) as f32;
+                let color = colors[block.id() as usize];
+                return image::Rgb(color.map(|c| (c as f32 * shade) as u8));
+            }
+            image::Rgb([0, 0, 0])
+        })
+    }
+
+    /// writes every chunk mesh into one obj file, each chunk is its own object
+    pub fn write_obj(&self, out: &mut impl Write) -> Result<(), ExportError> {
+        let atlas = TextureHandles::headless(BlockType::iter());
+        // obj indexes count up across the whole file and start at 1
+        let mut offset = 1;
+        for id in self.chunks() {
+            let mesh = block_on(Chunk::gen_mesh(id, self.data.clone(), atlas.clone()))?;
+            let Some(VertexAttributeValues::Uint32x2(packed)) = mesh.attribute(ATTRIBUTE_VOXEL)
+            else {
+                continue;
+            };
+            if packed.is_empty() {
+                continue;
+            }
+            let vertices = packed
+                .iter()
+                .map(|packed| ChunkVertex::unpack(*packed))
+                .collect::<Vec<_>>();
+            let corner = (id.0 * CHUNK_SIZE).as_vec3();
+            writeln!(out, "o chunk_{}_{}_{}", id.x(), id.y(), id.z())?;
+            for vertex in vertices.iter() {
+                let Vec3 { x, y, z } = vertex.position + corner;
+                writeln!(out, "v {} {} {}", x, y, z)?;
+            }
+            for vertex in vertices.iter() {
+                writeln!(out, "vt {} {}", vertex.uv.x, 1. - vertex.uv.y)?;
+            }
+            if let Some(Indices::U32(indices)) = mesh.indices() {
+                for face in indices.chunks_exact(3) {
+                    let [a, b, c] = [face[0], face[1], face[2]].map(|i| i as usize + offset);
+                    writeln!(out, "f {a}/{a} {b}/{b} {c}/{c}")?;
+                }
+            }
+            offset += vertices.len();
+        }
+        Ok(())
+    }
+}
+
+/// generates the region described by args and writes the requested files into args.out
+pub fn export(seed: u64, args: &ExportArgs) -> Result<(), ExportError> {
+    std::fs::create_dir_all(&args.out)?;
+    let region = Region::generate(seed, IVec2::new(args.x, args.z), args.radius)?;
+
+    let path = args.out.join("map.png");
+    region.top_down_image().save(&path)?;
+    println!("Wrote {}", path.display());
+
+    let path = args.out.join("histogram.csv");
+    write_histogram(&region, &path)?;
+    println!("Wrote {}", path.display());
+
+    if args.obj {
+        let path = args.out.join("terrain.obj");
+        let mut out = BufWriter::new(File::create(&path)?);
+        region.write_obj(&mut out)?;
+        out.flush()?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
+
+fn write_histogram(region: &Region, path: &Path) -> Result<(), ExportError> {
+    let mut out = BufWriter::new(File::create(path)?);
+    write!(out, "chunk_x,chunk_y,chunk_z")?;
+    for block in BlockType::iter() {
+        write!(out, ",{:?}", block)?;
+    }
+    writeln!(out)?;
+    for id in region.chunks() {
+        write!(out, "{},{},{}", id.x(), id.y(), id.z())?;
+        for (_, count) in region.histogram(id) {
+            write!(out, ",{}", count)?;
+        }
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[test]
+fn test_export_region() {
+    let region = Region::generate(3, IVec2::ZERO, 0).unwrap();
+    assert_eq!(region.chunks().count(), WORLD_CHUNKS as usize);
+    let bottom = region.histogram(ChunkId::new(0, 0, 0));
+    assert_eq!(
+        bottom.iter().map(|(_, count)| count).sum::<usize>(),
+        CHUNK_VOLUME as usize
+    );
+    // the bottom layer is all bedrock
+    assert!(bottom.contains(&(BlockType::Bedrock, CHUNK_AREA as usize)));
+
+    let image = region.top_down_image();
+    assert_eq!(image.dimensions(), (CHUNK_SIZE as u32, CHUNK_SIZE as u32));
+    assert!(image.pixels().all(|pixel| pixel.0 != [0, 0, 0]));
+
+    let mut obj = Vec::new();
+    region.write_obj(&mut obj).unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    assert!(obj.lines().any(|line| line.starts_with("f ")));
+}
@@ -1,22 +1,82 @@
-use bevy::{ecs::world::Command, prelude::*, render::render_asset::RenderAssetUsages, utils::HashMap};
-use indexmap::IndexSet;
+use std::time::{Duration, SystemTime};
 
-use crate::blocks::BlockType;
+use bevy::{
+    asset::LoadState,
+    ecs::world::Command,
+    image::{ImageAddressMode, ImageFilterMode, ImageSampler, ImageSamplerDescriptor},
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+            TextureViewDescriptor, TextureViewDimension,
+        },
+    },
+    time::common_conditions::on_timer,
+    utils::HashMap,
+};
+use indexmap::{IndexMap, IndexSet};
+use strum::IntoEnumIterator;
+
+use crate::{
+    actions::{Action, Actions},
+    blocks::{BlockType, Direction},
+};
+
+mod animation;
+mod material;
+mod pack;
+mod procedural;
+
+use animation::{AnimatedLayer, AnimatedTextures, AnimationMeta};
+
+pub use material::{ChunkExtension, ChunkMaterial, ChunkVertex, TintPalette, ATTRIBUTE_VOXEL};
+pub use pack::SelectedPack;
 
 pub struct TexturePlugin;
 
 impl Plugin for TexturePlugin {
     fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<ChunkMaterial>::default());
         app.add_systems(
             Update,
-            build_texture_atlas.run_if(resource_exists::<TextureAtlasBuilder>),
+            (
+                build_texture_atlas.run_if(resource_exists::<TextureAtlasBuilder>),
+                next_resource_pack,
+                rebuild_texture_atlas.run_if(
+                    not(resource_added::<SelectedPack>).and(resource_changed::<SelectedPack>),
+                ),
+                reload_modified_textures,
+                watch_resource_pack.run_if(on_timer(Duration::from_secs(1))),
+                animation::animate_textures,
+                generate_missing_cracks.run_if(resource_exists::<CrackTextures>),
+            ),
         );
-        app.init_resource::<TextureHandles>();
+        app.init_resource::<TextureHandles>()
+            .init_resource::<SelectedPack>()
+            .init_resource::<AnimatedTextures>()
+            .add_event::<BlockTexturesChanged>();
     }
 }
 
+/// Sent when the block textures are rebuilt after the first time, so meshes can pick up new layers
+#[derive(Event)]
+pub struct BlockTexturesChanged;
+
+/// every texture that goes in the array
+#[derive(Resource)]
+struct TextureAtlasBuilder(IndexMap<Handle<Image>, TextureSource>);
+
+struct TextureSource {
+    /// the first face that uses the texture, what to generate if it doesn't load
+    block: BlockType,
+    direction: Direction,
+    animation: Option<AnimationMeta>,
+}
+
+/// the textures the current texture array was built from, kept so edits to them can be noticed
 #[derive(Resource)]
-struct TextureAtlasBuilder(IndexSet<Handle<Image>>);
+struct BlockTextureSources(Vec<Handle<Image>>);
 
 pub struct MakeTextureAtlas(Vec<BlockType>);
 impl MakeTextureAtlas {
@@ -26,41 +86,123 @@ impl MakeTextureAtlas {
 }
 impl Command for MakeTextureAtlas {
     fn apply(self, world: &mut World) {
-        let mut need_textures = IndexSet::with_capacity(self.0.len());
+        let mut need_textures = IndexMap::with_capacity(self.0.len());
         let mut map = HashMap::with_capacity(self.0.len());
-        let asset_server = world.resource::<AssetServer>();
+        let asset_server = world.resource::<AssetServer>().clone();
+        // textures the selected pack has replace the built in ones
+        let mut pack = world
+            .get_resource::<SelectedPack>()
+            .and_then(SelectedPack::open)
+            .and_then(|pack| {
+                let name = pack.name();
+                pack::PackTextures::new(pack)
+                    .map_err(|e| error!("Failed to read resource pack {}: {}", name, e))
+                    .ok()
+            });
         for block in self.0.iter() {
-            for path in block.get_texture_paths() {
-                let handle = asset_server.load::<_>(*path);
-                let temp = handle.clone_weak();
-                need_textures.insert(handle);
+            for direction in Direction::iter() {
+                let Some(path) = block.texture(direction) else {
+                    break;
+                };
+                let from_pack = pack.as_mut().and_then(|pack| {
+                    pack.load(
+                        &mut world.resource_mut::<Assets<Image>>(),
+                        *block,
+                        direction,
+                    )
+                });
+                let (handle, animation) = match from_pack {
+                    Some(loaded) => loaded,
+                    None => (
+                        asset_server.load::<Image>(path),
+                        AnimationMeta::from_assets(path),
+                    ),
+                };
+                let entry = need_textures.entry(handle);
+                let index = entry.index();
+                entry.or_insert(TextureSource {
+                    block: *block,
+                    direction,
+                    animation,
+                });
                 let map: &mut Vec<usize> = map.entry(*block).or_default();
-                map.push(need_textures.get_index_of(&temp).unwrap());
+                map.push(index);
             }
         }
-        if self.0.contains(&BlockType::Water) {
-            map.insert(BlockType::Water, vec![need_textures.len()]);
-        }
+        let cracks = std::array::from_fn(|stage| {
+            let path = crack_path(stage);
+            let from_pack = pack.as_mut().and_then(|pack| {
+                pack.load_path(
+                    &mut world.resource_mut::<Assets<Image>>(),
+                    pack::minecraft_path(&path)?,
+                )
+            });
+            match from_pack {
+                Some((handle, _)) => handle,
+                None => asset_server.load(path),
+            }
+        });
         world.resource_mut::<TextureHandles>().set_map(map);
-        world
-            .resource_mut::<TextureHandles>()
-            .set_len((need_textures.len() as f32).sqrt().ceil() as usize);
         world.insert_resource(TextureAtlasBuilder(need_textures));
+        world.insert_resource(CrackTextures(cracks));
+    }
+}
+
+/// how many stages a block goes through as it is broken
+pub const CRACK_STAGES: usize = 10;
+
+fn crack_path(stage: usize) -> String {
+    format!("PureBDcraft/textures/block/destroy_stage_{}.png", stage)
+}
+
+/// The texture drawn over a block being broken for each stage
+/// taken from the selected pack like block textures, stages that fail to load are generated
+#[derive(Resource)]
+pub struct CrackTextures([Handle<Image>; CRACK_STAGES]);
+
+impl CrackTextures {
+    pub fn get(&self, stage: usize) -> Handle<Image> {
+        self.0[stage.min(CRACK_STAGES - 1)].clone()
+    }
+}
+
+fn generate_missing_cracks(
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut cracks: ResMut<CrackTextures>,
+) {
+    for stage in 0..CRACK_STAGES {
+        let Some(LoadState::Failed(e)) = asset_server.get_load_state(cracks.0[stage].id()) else {
+            continue;
+        };
+        warn!("Failed to load crack texture, using a generated one: {}", e);
+        let width = 16;
+        cracks.0[stage] = images.add(Image::new(
+            Extent3d {
+                width,
+                height: width,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            procedural::crack_texture(stage, CRACK_STAGES, width),
+            ATLAS_FORMAT,
+            RenderAssetUsages::all(),
+        ));
     }
 }
 
 pub struct TextureHandlesInternal {
     water: Handle<StandardMaterial>,
-    atlas: Handle<StandardMaterial>,
+    atlas: Handle<ChunkMaterial>,
     block_map: HashMap<BlockType, Vec<usize>>,
-    len: usize,
+    tints: TintPalette,
 }
 
 #[derive(Resource, Clone)]
 pub struct TextureHandles(std::sync::Arc<std::sync::RwLock<TextureHandlesInternal>>);
 
 impl TextureHandles {
-    pub fn get_atlas(&self) -> Handle<StandardMaterial> {
+    pub fn get_atlas(&self) -> Handle<ChunkMaterial> {
         self.0.read().unwrap().atlas.clone()
     }
 
@@ -68,6 +210,22 @@ impl TextureHandles {
         self.0.read().unwrap().water.clone()
     }
 
+    /// which layer of the texture array a face of a block uses
+    pub fn get_face_index(&self, block: &BlockType, direction: Direction) -> Option<usize> {
+        let internal = self.0.read().unwrap();
+        internal
+            .block_map
+            .get(block)?
+            .get(direction as usize)
+            .copied()
+    }
+
+    /// which tint in the [`TintPalette`] a face of a block uses
+    pub fn get_face_tint(&self, block: &BlockType, direction: Direction) -> u8 {
+        self.0.read().unwrap().tints.get(block, direction)
+    }
+
+    /// the texture layer of each face of a block in [`Direction`] order
     pub fn get_indexes(&self, block: &BlockType) -> Vec<usize> {
         if let Some(i) = self.0.read().unwrap().block_map.get(block) {
             i.clone()
@@ -75,36 +233,58 @@ impl TextureHandles {
             vec![]
         }
     }
-    pub fn len(&self) -> usize {
-        self.0.read().unwrap().len
-    }
-
-    pub fn set_len(&mut self, len: usize) {
-        self.0.write().unwrap().len = len;
-    }
-
     pub fn set_map(&mut self, map: HashMap<BlockType, Vec<usize>>) {
         self.0.write().unwrap().block_map = map;
     }
+
+    /// texture layers laid out the same way as [`MakeTextureAtlas`] but without loading any images
+    /// so meshes can be built without an [`AssetServer`]
+    pub fn headless(blocks: impl Iterator<Item = BlockType>) -> TextureHandles {
+        let blocks = blocks.collect::<Vec<_>>();
+        let mut paths = IndexSet::new();
+        let mut map = HashMap::with_capacity(blocks.len());
+        for block in blocks.iter() {
+            for direction in Direction::iter() {
+                let Some(path) = block.texture(direction) else {
+                    break;
+                };
+                let (index, _) = paths.insert_full(path);
+                let map: &mut Vec<usize> = map.entry(*block).or_default();
+                map.push(index);
+            }
+        }
+        TextureHandles(std::sync::Arc::new(std::sync::RwLock::new(
+            TextureHandlesInternal {
+                water: Handle::default(),
+                atlas: Handle::default(),
+                block_map: map,
+                tints: TintPalette::new(blocks.into_iter()),
+            },
+        )))
+    }
 }
 
 #[derive(Resource)]
-struct MainTexture(Handle<Image>);
+struct BlockTextures(Handle<Image>);
 
 impl FromWorld for TextureHandles {
     fn from_world(world: &mut World) -> Self {
-        let main_image = world
-            .resource_mut::<Assets<Image>>()
-            .reserve_handle();
-        world.insert_resource(MainTexture(main_image.clone()));
+        let block_textures = world.resource_mut::<Assets<Image>>().reserve_handle();
+        world.insert_resource(BlockTextures(block_textures.clone()));
+        let tints = TintPalette::new(BlockType::iter());
         let texture = world
-            .resource_mut::<Assets<StandardMaterial>>()
-            .add(StandardMaterial {
-                base_color_texture: Some(main_image),
-                metallic: 0.,
-                reflectance: 0.,
-                alpha_mode: AlphaMode::Mask(0.1),
-                ..Default::default()
+            .resource_mut::<Assets<ChunkMaterial>>()
+            .add(ChunkMaterial {
+                base: StandardMaterial {
+                    metallic: 0.,
+                    reflectance: 0.,
+                    alpha_mode: AlphaMode::Mask(0.1),
+                    ..Default::default()
+                },
+                extension: ChunkExtension {
+                    textures: block_textures,
+                    tints: tints.colors(),
+                },
             });
         let water_img = world.resource::<AssetServer>().load("Water.png");
         let water = world
@@ -123,98 +303,380 @@ impl FromWorld for TextureHandles {
                 water,
                 atlas: texture,
                 block_map: HashMap::new(),
-                len: 0,
+                tints,
             },
         )))
     }
 }
 
+/// the average colour of a face of a block with its tint, for drawing blocks without the texture array
+/// read straight from the texture in assets or generated if that can't be read like the game does
+pub fn face_color(block: BlockType, direction: Direction) -> [u8; 3] {
+    let Some(path) = block.texture(direction) else {
+        return [0; 3];
+    };
+    let rgba = match image::open(std::path::Path::new("assets").join(path)) {
+        Ok(image) => image.to_rgba8().into_raw(),
+        Err(_) => procedural::procedural_texture(block, direction, 16),
+    };
+    let tint = block.tint(direction);
+    let average = average_color(&rgba);
+    std::array::from_fn(|i| (average[i] as f32 * tint[i]).clamp(0., 255.) as u8)
+}
+
+/// the average of rgba8 pixels weighted by how opaque they are
+fn average_color(rgba: &[u8]) -> [u8; 3] {
+    let mut sum = [0.; 3];
+    let mut weight = 0.;
+    for pixel in rgba.chunks_exact(PIXEL_SIZE) {
+        let alpha = pixel[3] as f32;
+        for (sum, channel) in sum.iter_mut().zip(pixel) {
+            *sum += *channel as f32 * alpha;
+        }
+        weight += alpha;
+    }
+    if weight == 0. {
+        return [0; 3];
+    }
+    sum.map(|sum| (sum / weight) as u8)
+}
+
+/// how many bytes a pixel takes in the texture array
+const PIXEL_SIZE: usize = 4;
+const ATLAS_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+#[allow(clippy::too_many_arguments)]
 fn build_texture_atlas(
     mut commands: Commands,
     atlas_builder: Res<TextureAtlasBuilder>,
     asset_server: Res<AssetServer>,
     mut images: ResMut<Assets<Image>>,
-    main_image: Res<MainTexture>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+    block_textures: Res<BlockTextures>,
+    handles: Res<TextureHandles>,
+    mut changed: EventWriter<BlockTexturesChanged>,
 ) {
-    if !atlas_builder.0.iter().map(|handle| {asset_server.is_loaded_with_dependencies(handle.id())}).fold(true, |acc, mk| acc && mk) {
-        return;
+    // wait until every texture has either loaded or failed
+    let mut failed = Vec::new();
+    for handle in atlas_builder.0.keys() {
+        match asset_server.get_load_state(handle.id()) {
+            Some(LoadState::Failed(e)) => failed.push((handle.id(), e)),
+            Some(LoadState::Loaded) if asset_server.is_loaded_with_dependencies(handle.id()) => {}
+            // textures from a resource pack are added directly
+            None if images.contains(handle.id()) => {}
+            _ => return,
+        }
     }
-    let first = images
-        .get(&atlas_builder.0[0])
-        .expect("All images to be built");
-    let atlas_size = ((atlas_builder.0.len() + 1) as f32).sqrt().ceil() as usize;
-    let width = first.texture_descriptor.size.width as usize;
-    let format = first.texture_descriptor.format;
-    let pixed_size = format.block_copy_size(None).unwrap() as usize;
-    let mut atlas_data = vec![0; (atlas_size * atlas_size * width * width * pixed_size) as usize];
-    let mut has_water = false;
-    'y: for y in 0..atlas_size {
-        for x in 0..atlas_size {
-            let Some(handle) = atlas_builder.0.get_index(y * atlas_size + x) else {
-            if has_water {
-                break 'y;
-            }
-            has_water = true;
-            fill_from(
-                &mut atlas_data,
-                x,
-                y,
-                atlas_size,
-                width,
-                &vec![255; width * width * pixed_size],
-                pixed_size,
-            );
-            continue;
-            };
-            let Some(image) = images.get(handle) else {continue;};
-            fill_from(
-                &mut atlas_data,
-                x,
-                y,
-                atlas_size,
+    for (_, e) in failed.iter() {
+        warn!("Failed to load block texture, using a generated one: {}", e);
+    }
+
+    // every layer is scaled to the biggest texture so nothing loses detail
+    let width = atlas_builder
+        .0
+        .keys()
+        .filter_map(|handle| images.get(handle))
+        .map(|image| image.width().min(image.height()))
+        .max()
+        .unwrap_or(16);
+    let mut layers = Vec::with_capacity(atlas_builder.0.len());
+    let mut animated = Vec::new();
+    for (i, (handle, source)) in atlas_builder.0.iter().enumerate() {
+        let path = handle
+            .path()
+            .map_or_else(|| format!("{:?}", handle.id()), |path| path.to_string());
+        let generated = || {
+            vec![procedural::procedural_texture(
+                source.block,
+                source.direction,
                 width,
-                &image.data,
-                pixed_size,
-            );
+            )]
+        };
+        let frames = match images.get(handle) {
+            Some(image) => {
+                prepare_frames(image, width, source.animation.is_some()).unwrap_or_else(|e| {
+                    error!("Can't use {} as a block texture: {}", path, e);
+                    generated()
+                })
+            }
+            None => {
+                if !failed.iter().any(|(id, _)| *id == handle.id()) {
+                    error!("{} is not loaded", path);
+                }
+                generated()
+            }
+        };
+        let mut frames = frames
+            .iter()
+            .map(|frame| mip_chain(frame, width))
+            .collect::<Vec<_>>();
+        match &source.animation {
+            Some(animation) if frames.len() > 1 => {
+                let sequence = animation.sequence(frames.len() as u32);
+                let layer = AnimatedLayer::new(i, frames, sequence);
+                layers.push(layer.first_frame().to_vec());
+                animated.push(layer);
+            }
+            _ => layers.push(frames.swap_remove(0)),
         }
     }
-    let size = bevy::render::render_resource::Extent3d {
-        width: (width * atlas_size) as u32,
-        height: (width * atlas_size) as u32,
-        depth_or_array_layers: 1,
-    };
-    let _ = images.insert(
-        main_image.0.id(),
-        Image::new(
-            size,
-            bevy::render::render_resource::TextureDimension::D2,
-            atlas_data,
-            format,
-            RenderAssetUsages::all()
-        ),
-    );
+    commands.insert_resource(AnimatedTextures::new(animated));
+    let rebuilt = images.contains(block_textures.0.id());
+    let _ = images.insert(block_textures.0.id(), texture_array(&layers, width));
+    // the material has to be prepared again to bind the new texture
+    materials.get_mut(&handles.get_atlas());
+    if rebuilt {
+        changed.send(BlockTexturesChanged);
+    }
     commands.remove_resource::<TextureAtlasBuilder>();
+    commands.insert_resource(BlockTextureSources(
+        atlas_builder.0.keys().cloned().collect(),
+    ));
+}
+
+fn rebuild_texture_atlas(mut commands: Commands) {
+    commands.queue(MakeTextureAtlas::new(BlockType::iter()));
 }
 
-fn fill_from(
-    data: &mut Vec<u8>,
-    offset_x: usize,
-    offset_y: usize,
-    atlas_size: usize,
-    block_size: usize,
-    image: &[u8],
-    pixel_size: usize,
+fn next_resource_pack(actions: Actions, mut selected: ResMut<SelectedPack>) {
+    if actions.just_pressed(Action::NextResourcePack) {
+        selected.next();
+        info!(
+            "Resource pack: {}",
+            selected.0.as_deref().unwrap_or("built in")
+        );
+    }
+}
+
+/// rebuilds the texture array when one of the built in textures is edited
+fn reload_modified_textures(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<Image>>,
+    sources: Option<Res<BlockTextureSources>>,
+) {
+    let Some(sources) = sources else {
+        events.clear();
+        return;
+    };
+    let modified = events.read().any(|event| match event {
+        AssetEvent::Modified { id } => sources.0.iter().any(|handle| handle.id() == *id),
+        _ => false,
+    });
+    if modified {
+        commands.queue(MakeTextureAtlas::new(BlockType::iter()));
+    }
+}
+
+/// rebuilds the texture array when anything in the selected pack is edited
+fn watch_resource_pack(
+    mut commands: Commands,
+    selected: Res<SelectedPack>,
+    mut last: Local<Option<(SelectedPack, SystemTime)>>,
 ) {
-    let x_off = offset_x * block_size;
-    let y_off = offset_y * block_size * block_size * atlas_size;
-    for y in 0..block_size {
-        for x in 0..block_size {
-            let index = ((y_off + y * block_size * atlas_size) + (x_off + x)) * pixel_size;
-            let imdex = ((y * block_size) + x) * pixel_size;
-            data[index] = image[imdex];
-            data[index + 1] = image[imdex + 1];
-            data[index + 2] = image[imdex + 2];
-            data[index + 3] = image[imdex + 3];
+    let Some(modified) = selected.path().and_then(|path| pack::pack_modified(&path)) else {
+        *last = None;
+        return;
+    };
+    if let Some((pack, time)) = last.as_ref() {
+        // switching packs is handled by rebuild_texture_atlas
+        if *pack == *selected && *time < modified {
+            commands.queue(MakeTextureAtlas::new(BlockType::iter()));
+        }
+    }
+    *last = Some((selected.clone(), modified));
+}
+
+/// how many mip levels a width x width texture has down to 1x1
+fn mip_levels(width: u32) -> u32 {
+    width.max(1).ilog2() + 1
+}
+
+/// a width x width rgba8 tile followed by each of its mips down to 1x1
+fn mip_chain(tile: &[u8], width: u32) -> Vec<u8> {
+    let mut mip = image::RgbaImage::from_raw(width, width, tile.to_vec())
+        .expect("block texture layers are width x width");
+    let mut data = mip.as_raw().clone();
+    for level in 1..mip_levels(width) {
+        let size = (width >> level).max(1);
+        mip = image::imageops::resize(&mip, size, size, image::imageops::FilterType::Triangle);
+        data.extend_from_slice(&mip);
+    }
+    data
+}
+
+/// stacks layers made by [`mip_chain`] into an array texture
+fn texture_array(layers: &[Vec<u8>], width: u32) -> Image {
+    let mip_level_count = mip_levels(width);
+    Image {
+        // wgpu wants each layer followed by its mips
+        data: layers.concat(),
+        texture_descriptor: TextureDescriptor {
+            label: Some("block_textures"),
+            size: Extent3d {
+                width,
+                height: width,
+                depth_or_array_layers: layers.len() as u32,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: ATLAS_FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        texture_view_descriptor: Some(TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..Default::default()
+        }),
+        // sharp up close, blended with distance instead of shimmering
+        sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
+            address_mode_u: ImageAddressMode::Repeat,
+            address_mode_v: ImageAddressMode::Repeat,
+            mag_filter: ImageFilterMode::Nearest,
+            min_filter: ImageFilterMode::Nearest,
+            mipmap_filter: ImageFilterMode::Linear,
+            ..Default::default()
+        }),
+        asset_usage: RenderAssetUsages::all(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TileError {
+    #[error("unsupported format: {0}")]
+    Format(String),
+    #[error("image is empty")]
+    Empty,
+}
+
+/// converts an image into width x width tiles of srgb rgba8 pixels
+/// an animation is a vertical strip of square frames, anything else that is not square uses the top
+fn prepare_frames(image: &Image, width: u32, animated: bool) -> Result<Vec<Vec<u8>>, TileError> {
+    let srgb = image.texture_descriptor.format.is_srgb();
+    let size = image.width().min(image.height());
+    if size == 0 {
+        return Err(TileError::Empty);
+    }
+    let mut rgba = image
+        .clone()
+        .try_into_dynamic()
+        .map_err(|e| TileError::Format(e.to_string()))?
+        .to_rgba8();
+    if !srgb {
+        for pixel in rgba.pixels_mut() {
+            for channel in &mut pixel.0[..3] {
+                *channel = linear_to_srgb(*channel);
+            }
         }
     }
+    let frames = if animated { image.height() / size } else { 1 };
+    let tiles = (0..frames)
+        .map(|frame| {
+            let square = image::imageops::crop_imm(&rgba, 0, frame * size, size, size).to_image();
+            let tile = if size == width {
+                square
+            } else {
+                image::imageops::resize(&square, width, width, image::imageops::FilterType::Nearest)
+            };
+            tile.into_raw()
+        })
+        .collect();
+    Ok(tiles)
+}
+
+fn linear_to_srgb(channel: u8) -> u8 {
+    let linear = channel as f32 / 255.;
+    let srgb = if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    };
+    (srgb * 255.).round() as u8
+}
+
+#[test]
+fn test_prepare_frames() {
+    let size = |width| Extent3d {
+        width,
+        height: width,
+        depth_or_array_layers: 1,
+    };
+    // a 32x32 linear texture is shrunk and converted to srgb
+    let big = Image::new_fill(
+        size(32),
+        TextureDimension::D2,
+        &[64, 128, 255, 255],
+        TextureFormat::Rgba8Unorm,
+        RenderAssetUsages::all(),
+    );
+    let tile = prepare_frames(&big, 16, false).unwrap().remove(0);
+    assert_eq!(tile.len(), 16 * 16 * PIXEL_SIZE);
+    assert_eq!(
+        tile[..4],
+        [linear_to_srgb(64), linear_to_srgb(128), 255, 255]
+    );
+
+    // an 8x8 srgb texture is scaled up without changing colour
+    let small = Image::new_fill(
+        size(8),
+        TextureDimension::D2,
+        &[10, 20, 30, 40],
+        ATLAS_FORMAT,
+        RenderAssetUsages::all(),
+    );
+    let tile = prepare_frames(&small, 16, false).unwrap().remove(0);
+    assert!(tile.chunks(4).all(|pixel| pixel == [10, 20, 30, 40]));
+
+    // an animation strip is split into its frames
+    let strip = Image::new_fill(
+        Extent3d {
+            width: 16,
+            height: 48,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[1, 2, 3, 255],
+        ATLAS_FORMAT,
+        RenderAssetUsages::all(),
+    );
+    assert_eq!(prepare_frames(&strip, 16, true).unwrap().len(), 3);
+    assert_eq!(prepare_frames(&strip, 16, false).unwrap().len(), 1);
+}
+
+#[test]
+fn test_texture_array() {
+    assert_eq!(mip_levels(16), 5);
+    assert_eq!(mip_levels(24), 5);
+    assert_eq!(mip_levels(1), 1);
+    let layers = vec![
+        mip_chain(
+            &procedural::procedural_texture(BlockType::GoldOre, Direction::Up, 16),
+            16,
+        ),
+        mip_chain(&[255; 16 * 16 * PIXEL_SIZE], 16),
+    ];
+    let image = texture_array(&layers, 16);
+    assert_eq!(image.texture_descriptor.size.depth_or_array_layers, 2);
+    assert_eq!(image.texture_descriptor.mip_level_count, 5);
+    // 16x16 + 8x8 + 4x4 + 2x2 + 1x1 pixels for each layer
+    let layer_len = (256 + 64 + 16 + 4 + 1) * PIXEL_SIZE;
+    assert_eq!(image.data.len(), layer_len * 2);
+    // the white layer stays white all the way down
+    assert!(image.data[layer_len..].iter().all(|c| *c == 255));
+    // the smallest mip of the ore is still opaque
+    assert_eq!(image.data[layer_len - 1], 255);
+}
+
+#[test]
+fn test_face_color() {
+    assert_eq!(
+        average_color(&[10, 20, 30, 255, 200, 200, 200, 0]),
+        [10, 20, 30]
+    );
+    assert_eq!(face_color(BlockType::Air, Direction::Up), [0; 3]);
+    // tinted the same way the mesher does
+    let [r, g, b] = face_color(BlockType::Grass, Direction::Up);
+    assert!(g > r && g > b);
+    let [r, g, b] = face_color(BlockType::Water, Direction::Up);
+    assert!(b > r && b > g);
 }
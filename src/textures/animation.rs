@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{BlockTextures, ChunkMaterial, TextureHandles};
+
+/// how long a minecraft tick is, frame times are counted in ticks
+const TICK: f32 = 1. / 20.;
+
+/// The part of a minecraft .mcmeta file that describes an animation
+/// the texture is a vertical strip of square frames
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct AnimationMeta {
+    /// how many ticks each frame is shown for
+    pub frametime: Option<u32>,
+    /// the order to show the frames in, every frame top to bottom if missing
+    pub frames: Option<Vec<FrameMeta>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum FrameMeta {
+    Index(u32),
+    Timed { index: u32, time: u32 },
+}
+
+#[derive(Deserialize)]
+struct McMeta {
+    animation: Option<AnimationMeta>,
+}
+
+impl AnimationMeta {
+    /// reads the animation out of the .mcmeta file for the texture at path
+    pub fn parse(path: &str, file: &[u8]) -> Option<AnimationMeta> {
+        match serde_json::from_slice::<McMeta>(file) {
+            Ok(meta) => meta.animation,
+            Err(e) => {
+                error!("Failed to parse {}.mcmeta: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// reads the .mcmeta file next to a texture in the assets folder, if there is one
+    pub fn from_assets(path: &str) -> Option<AnimationMeta> {
+        let file = std::fs::read(format!("assets/{}.mcmeta", path)).ok()?;
+        AnimationMeta::parse(path, &file)
+    }
+
+    /// which frame to show and for how many ticks, frames past the end of the strip are skipped
+    pub fn sequence(&self, frame_count: u32) -> Vec<(u32, u32)> {
+        let frametime = self.frametime.unwrap_or(1).max(1);
+        let sequence = match &self.frames {
+            Some(frames) => frames
+                .iter()
+                .map(|frame| match *frame {
+                    FrameMeta::Index(index) => (index, frametime),
+                    FrameMeta::Timed { index, time } => (index, time.max(1)),
+                })
+                .filter(|(index, _)| *index < frame_count)
+                .collect(),
+            None => (0..frame_count).map(|index| (index, frametime)).collect(),
+        };
+        if sequence.is_empty() {
+            vec![(0, frametime)]
+        } else {
+            sequence
+        }
+    }
+}
+
+/// A layer of the texture array that cycles through frames
+pub struct AnimatedLayer {
+    layer: usize,
+    /// every frame of the strip with its mips, laid out the same as a layer of the array
+    frames: Vec<Vec<u8>>,
+    sequence: Vec<(u32, u32)>,
+    step: usize,
+    ticks: u32,
+}
+
+impl AnimatedLayer {
+    pub fn new(layer: usize, frames: Vec<Vec<u8>>, sequence: Vec<(u32, u32)>) -> AnimatedLayer {
+        AnimatedLayer {
+            layer,
+            frames,
+            sequence,
+            step: 0,
+            ticks: 0,
+        }
+    }
+
+    /// the frame the animation starts on
+    pub fn first_frame(&self) -> &[u8] {
+        &self.frames[self.sequence[0].0 as usize]
+    }
+
+    /// moves on one tick returning the new frame if it changed
+    fn tick(&mut self) -> Option<&[u8]> {
+        self.ticks += 1;
+        if self.ticks < self.sequence[self.step].1 {
+            return None;
+        }
+        self.ticks = 0;
+        let last = self.sequence[self.step].0;
+        self.step = (self.step + 1) % self.sequence.len();
+        let next = self.sequence[self.step].0;
+        (next != last).then(|| self.frames[next as usize].as_slice())
+    }
+}
+
+/// Every animated layer in the current texture array
+#[derive(Resource, Default)]
+pub struct AnimatedTextures {
+    layers: Vec<AnimatedLayer>,
+    /// time since the last tick
+    elapsed: f32,
+}
+
+impl AnimatedTextures {
+    pub fn new(layers: Vec<AnimatedLayer>) -> AnimatedTextures {
+        AnimatedTextures {
+            layers,
+            elapsed: 0.,
+        }
+    }
+}
+
+/// copies the current frame of each animation into its layer of the texture array
+pub fn animate_textures(
+    time: Res<Time>,
+    mut animated: ResMut<AnimatedTextures>,
+    block_textures: Res<BlockTextures>,
+    handles: Res<TextureHandles>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<ChunkMaterial>>,
+) {
+    if animated.layers.is_empty() {
+        return;
+    }
+    animated.elapsed += time.delta_secs();
+    let mut changed = false;
+    while animated.elapsed >= TICK {
+        animated.elapsed -= TICK;
+        for layer in animated.layers.iter_mut() {
+            let index = layer.layer;
+            let Some(frame) = layer.tick() else {
+                continue;
+            };
+            let Some(image) = images.get_mut(block_textures.0.id()) else {
+                return;
+            };
+            let start = index * frame.len();
+            image.data[start..start + frame.len()].copy_from_slice(frame);
+            changed = true;
+        }
+    }
+    if changed {
+        // the material has to be prepared again to bind the new texture
+        materials.get_mut(&handles.get_atlas());
+    }
+}
+
+#[test]
+fn test_animation_meta() {
+    let meta = AnimationMeta::parse(
+        "water_still.png",
+        br#"{"animation": {"frametime": 2, "interpolate": true}}"#,
+    )
+    .unwrap();
+    assert_eq!(meta.sequence(3), [(0, 2), (1, 2), (2, 2)]);
+
+    let meta = AnimationMeta::parse(
+        "lava.png",
+        br#"{"animation": {"frames": [2, {"index": 0, "time": 5}, 7]}}"#,
+    )
+    .unwrap();
+    // frame 7 is past the end of a 3 frame strip
+    assert_eq!(meta.sequence(3), [(2, 1), (0, 5)]);
+
+    assert_eq!(AnimationMeta::parse("stone.png", b"{}"), None);
+    assert_eq!(AnimationMeta::parse("broken.png", b"{"), None);
+
+    let mut layer = AnimatedLayer::new(0, vec![vec![0], vec![1]], vec![(1, 2), (0, 1)]);
+    assert_eq!(layer.first_frame(), [1]);
+    assert_eq!(layer.tick(), None);
+    assert_eq!(layer.tick(), Some([0].as_slice()));
+    assert_eq!(layer.tick(), Some([1].as_slice()));
+}
@@ -0,0 +1,218 @@
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
+    prelude::*,
+    render::{
+        mesh::{MeshVertexAttribute, MeshVertexBufferLayoutRef},
+        render_resource::{
+            AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError,
+            VertexFormat,
+        },
+    },
+    utils::HashMap,
+};
+use strum::IntoEnumIterator;
+
+use crate::blocks::{BlockType, Direction};
+
+const SHADER_PATH: &str = "shaders/chunk.wgsl";
+
+/// how many different tints the chunk shader can look up
+pub const MAX_TINTS: usize = 64;
+
+/// a whole chunk vertex packed into two u32s, see [`ChunkVertex`]
+pub const ATTRIBUTE_VOXEL: MeshVertexAttribute =
+    MeshVertexAttribute::new("Voxel", 988540917, VertexFormat::Uint32x2);
+
+/// positions and uvs are snapped to a 16th of a block, one texel of a 16x16 texture
+const STEPS: f32 = 16.;
+
+/// Everything the chunk shader knows about a vertex before it is packed
+///
+/// the first u32 is x, y, z (9 bits each), the face (3 bits) and ao (2 bits)
+/// the second is u, v (5 bits each), light (4 bits), tint (6 bits) and the texture layer (12 bits)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkVertex {
+    /// position in the chunk, -0.5..=31.4
+    pub position: Vec3,
+    /// which way the face points, this is the normal
+    pub face: Direction,
+    /// 0..=1
+    pub uv: Vec2,
+    /// how many blocks around the corner are in the way of light 0..=3
+    pub ao: u8,
+    /// how much light the face gives off 0..=15
+    pub light: u8,
+    /// index into the tints of the [`ChunkExtension`]
+    pub tint: u8,
+    /// which layer of the block texture array the face samples from
+    pub layer: u32,
+}
+
+impl ChunkVertex {
+    pub fn pack(&self) -> [u32; 2] {
+        let [x, y, z] = ((self.position + 0.5) * STEPS)
+            .round()
+            .as_uvec3()
+            .to_array()
+            .map(|c| c.min(0x1ff));
+        let [u, v] = (self.uv * STEPS)
+            .round()
+            .as_uvec2()
+            .to_array()
+            .map(|c| c.min(0x1f));
+        [
+            x | (y << 9) | (z << 18) | ((self.face as u32) << 27) | ((self.ao.min(3) as u32) << 30),
+            u | (v << 5)
+                | ((self.light.min(15) as u32) << 10)
+                | (((self.tint as u32) & 0x3f) << 14)
+                | ((self.layer & 0xfff) << 20),
+        ]
+    }
+
+    pub fn unpack([a, b]: [u32; 2]) -> ChunkVertex {
+        let position = UVec3::new(a & 0x1ff, (a >> 9) & 0x1ff, (a >> 18) & 0x1ff);
+        ChunkVertex {
+            position: position.as_vec3() / STEPS - 0.5,
+            face: Direction::iter()
+                .nth(((a >> 27) & 0x7) as usize)
+                .unwrap_or(Direction::Up),
+            uv: UVec2::new(b & 0x1f, (b >> 5) & 0x1f).as_vec2() / STEPS,
+            ao: (a >> 30) as u8,
+            light: ((b >> 10) & 0xf) as u8,
+            tint: ((b >> 14) & 0x3f) as u8,
+            layer: b >> 20,
+        }
+    }
+}
+
+/// A per-block palette of the distinct face tints, the chunk shader looks faces up in this
+/// so meshes only store an index instead of a colour
+#[derive(Debug, Clone)]
+pub struct TintPalette {
+    /// index 0 is always white for faces without a tint
+    colors: Vec<[f32; 4]>,
+    faces: HashMap<BlockType, [u8; 6]>,
+}
+
+impl TintPalette {
+    pub fn new(blocks: impl Iterator<Item = BlockType>) -> TintPalette {
+        let mut colors = vec![[1.; 4]];
+        let mut faces = HashMap::new();
+        for block in blocks {
+            if block.properties().tint.is_none() {
+                continue;
+            }
+            let mut indexes = [0; 6];
+            for direction in Direction::iter() {
+                let tint = block.tint(direction);
+                let index = match colors.iter().position(|color| *color == tint) {
+                    Some(index) => index,
+                    None if colors.len() < MAX_TINTS => {
+                        colors.push(tint);
+                        colors.len() - 1
+                    }
+                    None => {
+                        warn!("Too many block tints, {:?} is drawn untinted", block);
+                        0
+                    }
+                };
+                indexes[direction as usize] = index as u8;
+            }
+            faces.insert(block, indexes);
+        }
+        TintPalette { colors, faces }
+    }
+
+    /// the index of the tint on a face of a block
+    pub fn get(&self, block: &BlockType, direction: Direction) -> u8 {
+        self.faces
+            .get(block)
+            .map_or(0, |faces| faces[direction as usize])
+    }
+
+    /// the colours laid out for the uniform in [`ChunkExtension`]
+    pub fn colors(&self) -> [Vec4; MAX_TINTS] {
+        let mut colors = [Vec4::ONE; MAX_TINTS];
+        for (i, color) in self.colors.iter().enumerate() {
+            colors[i] = Vec4::from_array(*color);
+        }
+        colors
+    }
+}
+
+/// What chunks are drawn with, lit like a [`StandardMaterial`] but with packed vertices
+/// textured from an array and tinted from a palette
+pub type ChunkMaterial = ExtendedMaterial<StandardMaterial, ChunkExtension>;
+
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct ChunkExtension {
+    /// every block texture as a layer of one array texture
+    #[texture(100, dimension = "2d_array")]
+    #[sampler(101)]
+    pub textures: Handle<Image>,
+    /// the colours of the [`TintPalette`]
+    #[uniform(102)]
+    pub tints: [Vec4; MAX_TINTS],
+}
+
+impl MaterialExtension for ChunkExtension {
+    fn vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn prepass_vertex_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn prepass_fragment_shader() -> ShaderRef {
+        SHADER_PATH.into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        // the prepass and shadows unpack the same vertices so they need the same layout
+        let vertex_layout = layout
+            .0
+            .get_layout(&[ATTRIBUTE_VOXEL.at_shader_location(0)])?;
+        descriptor.vertex.buffers = vec![vertex_layout];
+        Ok(())
+    }
+}
+
+#[test]
+fn test_chunk_vertex() {
+    let vertex = ChunkVertex {
+        position: Vec3::new(15.5, -0.5, 3.0625),
+        face: Direction::Back,
+        uv: Vec2::new(1., 0.5),
+        ao: 3,
+        light: 15,
+        tint: 63,
+        layer: 4095,
+    };
+    assert_eq!(ChunkVertex::unpack(vertex.pack()), vertex);
+    // out of range values are clamped rather than spilling into the next field
+    let clamped = ChunkVertex {
+        ao: 9,
+        light: 200,
+        ..vertex
+    };
+    assert_eq!(ChunkVertex::unpack(clamped.pack()), vertex);
+
+    let palette = TintPalette::new(BlockType::iter());
+    assert_eq!(palette.get(&BlockType::Stone, Direction::Up), 0);
+    assert_eq!(palette.get(&BlockType::Grass, Direction::Forward), 0);
+    let grass = palette.get(&BlockType::Grass, Direction::Up);
+    assert_eq!(
+        palette.colors()[grass as usize].to_array(),
+        BlockType::Grass.tint(Direction::Up)
+    );
+}
@@ -0,0 +1,313 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::{asset::RenderAssetUsages, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::AnimationMeta;
+use crate::blocks::{BlockFaces, BlockType, Direction};
+
+/// where resource packs are looked for, relative to the working directory
+pub const PACKS_DIR: &str = "resourcepacks";
+
+/// the file at the root of a pack that says which texture each block uses
+const MANIFEST: &str = "pack.ron";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PackError {
+    #[error("{0} is not in the pack")]
+    NotFound(String),
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("{0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("{0}")]
+    Manifest(#[from] ron::error::SpannedError),
+    #[error("{0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// What is in a pack.ron, every field can be left out
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PackManifest {
+    pub name: Option<String>,
+    /// paths inside the pack keyed by block name
+    pub textures: HashMap<String, BlockFaces<String>>,
+}
+
+/// A directory or zip of block textures
+#[derive(Debug, Clone)]
+pub struct ResourcePack {
+    path: PathBuf,
+    manifest: PackManifest,
+}
+
+impl ResourcePack {
+    /// opens a pack reading its manifest if it has one
+    pub fn open(path: impl Into<PathBuf>) -> Result<ResourcePack, PackError> {
+        let mut pack = ResourcePack {
+            path: path.into(),
+            manifest: PackManifest::default(),
+        };
+        match pack.reader()?.read(MANIFEST) {
+            Ok(file) => pack.manifest = ron::de::from_bytes(&file)?,
+            Err(PackError::NotFound(_)) => {}
+            Err(e) => return Err(e),
+        }
+        Ok(pack)
+    }
+
+    pub fn name(&self) -> String {
+        match &self.manifest.name {
+            Some(name) => name.clone(),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    pub fn reader(&self) -> Result<PackReader, PackError> {
+        if self.path.is_dir() {
+            Ok(PackReader::Dir(self.path.clone()))
+        } else {
+            Ok(PackReader::Zip(zip::ZipArchive::new(File::open(
+                &self.path,
+            )?)?))
+        }
+    }
+
+    /// the path inside the pack of the texture on a face of a block
+    /// blocks missing from the manifest are looked for where minecraft packs keep them
+    pub fn texture_path(&self, block: BlockType, direction: Direction) -> Option<String> {
+        if let Some(faces) = self.manifest.textures.get(&block.properties().name) {
+            return Some(faces.get(direction).clone());
+        }
+        minecraft_path(block.texture(direction)?)
+    }
+}
+
+/// where a minecraft pack keeps one of the built in textures, the first directory is swapped for the pack layout
+pub fn minecraft_path(asset: &str) -> Option<String> {
+    let (_, path) = asset.split_once('/')?;
+    Some(format!("assets/minecraft/{}", path))
+}
+
+/// the newest modification time of anything in a pack, changes when a texture is edited
+pub fn pack_modified(path: &Path) -> Option<SystemTime> {
+    if !path.is_dir() {
+        return std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    }
+    let mut newest = None;
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                dirs.push(entry.path());
+            } else if let Ok(modified) = meta.modified() {
+                newest = newest.max(Some(modified));
+            }
+        }
+    }
+    newest
+}
+
+/// Reads files out of a [`ResourcePack`]
+pub enum PackReader {
+    Dir(PathBuf),
+    Zip(zip::ZipArchive<File>),
+}
+
+impl PackReader {
+    pub fn read(&mut self, path: &str) -> Result<Vec<u8>, PackError> {
+        let mut data = Vec::new();
+        match self {
+            PackReader::Dir(root) => match File::open(root.join(path)) {
+                Ok(mut file) => file.read_to_end(&mut data)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    return Err(PackError::NotFound(path.to_string()))
+                }
+                Err(e) => return Err(e.into()),
+            },
+            PackReader::Zip(zip) => match zip.by_name(path) {
+                Ok(mut file) => file.read_to_end(&mut data)?,
+                Err(zip::result::ZipError::FileNotFound) => {
+                    return Err(PackError::NotFound(path.to_string()))
+                }
+                Err(e) => return Err(e.into()),
+            },
+        };
+        Ok(data)
+    }
+
+    pub fn read_image(&mut self, path: &str) -> Result<Image, PackError> {
+        let image = image::load_from_memory(&self.read(path)?)?;
+        Ok(Image::from_dynamic(image, true, RenderAssetUsages::all()))
+    }
+}
+
+/// Loads block textures out of a pack into [`Assets<Image>`], each file is only read once
+pub struct PackTextures {
+    pack: ResourcePack,
+    reader: PackReader,
+    loaded: HashMap<String, (Handle<Image>, Option<AnimationMeta>)>,
+}
+
+impl PackTextures {
+    pub fn new(pack: ResourcePack) -> Result<PackTextures, PackError> {
+        Ok(PackTextures {
+            reader: pack.reader()?,
+            pack,
+            loaded: HashMap::new(),
+        })
+    }
+
+    /// loads the texture and its animation
+    /// returns none if the pack doesn't have the texture so the built in one can be used
+    pub fn load(
+        &mut self,
+        images: &mut Assets<Image>,
+        block: BlockType,
+        direction: Direction,
+    ) -> Option<(Handle<Image>, Option<AnimationMeta>)> {
+        let path = self.pack.texture_path(block, direction)?;
+        self.load_path(images, path)
+    }
+
+    /// like [`PackTextures::load`] but for any path inside the pack
+    pub fn load_path(
+        &mut self,
+        images: &mut Assets<Image>,
+        path: String,
+    ) -> Option<(Handle<Image>, Option<AnimationMeta>)> {
+        if let Some(loaded) = self.loaded.get(&path) {
+            return Some(loaded.clone());
+        }
+        match self.reader.read_image(&path) {
+            Ok(image) => {
+                let animation = match self.reader.read(&format!("{}.mcmeta", path)) {
+                    Ok(file) => AnimationMeta::parse(&path, &file),
+                    Err(PackError::NotFound(_)) => None,
+                    Err(e) => {
+                        error!("Failed to read {}.mcmeta: {}", path, e);
+                        None
+                    }
+                };
+                let loaded = (images.add(image), animation);
+                self.loaded.insert(path, loaded.clone());
+                Some(loaded)
+            }
+            Err(PackError::NotFound(_)) => None,
+            Err(e) => {
+                error!("Failed to load {} from {}: {}", path, self.pack.name(), e);
+                None
+            }
+        }
+    }
+}
+
+/// The name of the pack in [`PACKS_DIR`] to take block textures from, none uses the built in paths
+#[derive(Resource, Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SelectedPack(pub Option<String>);
+
+impl SelectedPack {
+    pub fn path(&self) -> Option<PathBuf> {
+        Some(Path::new(PACKS_DIR).join(self.0.as_ref()?))
+    }
+
+    pub fn open(&self) -> Option<ResourcePack> {
+        let name = self.0.as_ref()?;
+        match ResourcePack::open(Path::new(PACKS_DIR).join(name)) {
+            Ok(pack) => Some(pack),
+            Err(e) => {
+                error!("Failed to open resource pack {}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    /// moves on to the next pack in [`PACKS_DIR`], after the last one it goes back to none
+    pub fn next(&mut self) {
+        let packs = available_packs(Path::new(PACKS_DIR));
+        let next = match &self.0 {
+            Some(current) => packs
+                .iter()
+                .position(|pack| pack == current)
+                .map_or(0, |i| i + 1),
+            None => 0,
+        };
+        self.0 = packs.get(next).cloned();
+    }
+}
+
+/// the directories and zips in dir sorted by name
+pub fn available_packs(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut packs = entries
+        .flatten()
+        .filter(|entry| {
+            let path = entry.path();
+            path.is_dir() || path.extension().is_some_and(|ext| ext == "zip")
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    packs.sort();
+    packs
+}
+
+#[test]
+fn test_resource_pack() {
+    let dir = std::env::temp_dir().join(format!("resource_pack_test_{}", std::process::id()));
+    let pack_dir = dir.join("dir_pack");
+    std::fs::create_dir_all(pack_dir.join("blocks")).unwrap();
+    std::fs::write(
+        pack_dir.join(MANIFEST),
+        r#"(name: "Test", textures: {"Stone": All("blocks/stone.png")})"#,
+    )
+    .unwrap();
+    image::RgbaImage::from_pixel(4, 4, image::Rgba([1, 2, 3, 255]))
+        .save(pack_dir.join("blocks/stone.png"))
+        .unwrap();
+    std::fs::write(dir.join("zip_pack.zip"), b"").unwrap();
+    std::fs::write(dir.join("notes.txt"), b"").unwrap();
+    assert_eq!(available_packs(&dir), ["dir_pack", "zip_pack.zip"]);
+
+    let pack = ResourcePack::open(&pack_dir).unwrap();
+    assert_eq!(pack.name(), "Test");
+    assert!(pack_modified(&pack_dir).is_some());
+    let path = pack.texture_path(BlockType::Stone, Direction::Up).unwrap();
+    assert_eq!(path, "blocks/stone.png");
+    let mut textures = PackTextures::new(pack.clone()).unwrap();
+    let mut images = Assets::<Image>::default();
+    let stone = textures.load(&mut images, BlockType::Stone, Direction::Up);
+    let (stone, animation) = stone.unwrap();
+    assert_eq!(images.get(&stone).unwrap().width(), 4);
+    assert_eq!(animation, None);
+    assert!(textures
+        .load(&mut images, BlockType::Dirt, Direction::Up)
+        .is_none());
+    // blocks not in the manifest use the minecraft layout
+    assert_eq!(
+        pack.texture_path(BlockType::Dirt, Direction::Up).as_deref(),
+        Some("assets/minecraft/textures/block/dirt.png")
+    );
+    assert!(matches!(
+        pack.reader().unwrap().read("missing.png"),
+        Err(PackError::NotFound(_))
+    ));
+    // an empty file is not a zip
+    assert!(ResourcePack::open(dir.join("zip_pack.zip")).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
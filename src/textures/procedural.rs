@@ -0,0 +1,161 @@
+use std::hash::{BuildHasher, Hash, Hasher};
+
+use noise::{NoiseFn, Perlin};
+
+use super::PIXEL_SIZE;
+use crate::blocks::{BlockType, Direction};
+
+/// how many texels across the patterns are designed for, bigger textures repeat pixels
+const TEXELS: u32 = 16;
+
+/// How a face is drawn
+struct Pattern {
+    color: [u8; 3],
+    /// how much the brightness wanders, 0..1
+    variation: f32,
+    /// blobs of a second colour like ore in stone
+    spots: Option<[u8; 3]>,
+    /// rows of a second colour along the top like the grass on the side of dirt
+    top: Option<[u8; 3]>,
+}
+
+impl Pattern {
+    const fn plain(color: [u8; 3], variation: f32) -> Pattern {
+        Pattern {
+            color,
+            variation,
+            spots: None,
+            top: None,
+        }
+    }
+
+    const fn ore(spots: [u8; 3]) -> Pattern {
+        Pattern {
+            spots: Some(spots),
+            ..STONE
+        }
+    }
+}
+
+const STONE: Pattern = Pattern::plain([125, 125, 125], 0.15);
+const DIRT: Pattern = Pattern::plain([134, 96, 67], 0.2);
+
+fn pattern(block: BlockType, direction: Direction) -> Pattern {
+    match block {
+        BlockType::Bedrock => Pattern::plain([60, 60, 60], 0.6),
+        BlockType::Gravel => Pattern::plain([130, 124, 122], 0.4),
+        BlockType::Dirt => DIRT,
+        BlockType::Stone => STONE,
+        BlockType::Sand => Pattern::plain([219, 207, 163], 0.08),
+        BlockType::GoldOre => Pattern::ore([252, 238, 75]),
+        BlockType::IronOre => Pattern::ore([216, 175, 147]),
+        BlockType::CoalOre => Pattern::ore([30, 30, 30]),
+        // the top is tinted green by the mesher so it is grey like a real grass texture
+        BlockType::Grass => match direction {
+            Direction::Up => Pattern::plain([190, 190, 190], 0.15),
+            Direction::Down => DIRT,
+            _ => Pattern {
+                top: Some([89, 145, 62]),
+                ..DIRT
+            },
+        },
+        // tinted blue by the mesher like the real water texture
+        BlockType::Water => Pattern::plain([200, 200, 200], 0.1),
+        // blocks added in blocks.ron get a colour from their name
+        _ => {
+            let mut hasher = bevy::utils::FixedState.build_hasher();
+            block.properties().name.hash(&mut hasher);
+            let [r, g, b, ..] = hasher.finish().to_le_bytes();
+            Pattern::plain([r, g, b], 0.2)
+        }
+    }
+}
+
+/// a width x width srgb rgba8 texture for a face of a block made from noise
+/// used when the real texture can't be loaded so every block still looks different
+pub fn procedural_texture(block: BlockType, direction: Direction, width: u32) -> Vec<u8> {
+    let pattern = pattern(block, direction);
+    let noise = Perlin::new(block.id() as u32);
+    let spots = Perlin::new(block.id() as u32 + 1);
+    let mut data = Vec::with_capacity((width * width) as usize * PIXEL_SIZE);
+    for y in 0..width {
+        for x in 0..width {
+            let (tx, ty) = (x * TEXELS / width, y * TEXELS / width);
+            let point = [tx as f64 * 0.3, ty as f64 * 0.3];
+            let mut shade = noise.get(point) as f32 + (grain(tx, ty) - 0.5);
+            let mut color = pattern.color;
+            if let Some(top) = pattern.top {
+                // a ragged edge so it looks like it is hanging over the side
+                if ty < 3 + grain(tx, 0).round() as u32 {
+                    color = top;
+                }
+            }
+            if let Some(spot) = pattern.spots {
+                if spots.get([point[0] * 2., point[1] * 2.]) > 0.35 {
+                    color = spot;
+                    shade *= 0.5;
+                }
+            }
+            let brightness = 1. + shade * pattern.variation;
+            data.extend(color.map(|c| (c as f32 * brightness).clamp(0., 255.) as u8));
+            data.push(255);
+        }
+    }
+    data
+}
+
+/// a width x width srgb rgba8 crack for the overlay on a block being broken
+/// white everywhere but the crack so it leaves the block alone when multiplied over it
+pub fn crack_texture(stage: usize, stages: usize, width: u32) -> Vec<u8> {
+    // the crack spreads out from the middle as the block breaks
+    let middle = TEXELS as f32 / 2.;
+    let reach = (stage + 1) as f32 / stages as f32 * middle;
+    let mut data = Vec::with_capacity((width * width) as usize * PIXEL_SIZE);
+    for y in 0..width {
+        for x in 0..width {
+            let (tx, ty) = (x * TEXELS / width, y * TEXELS / width);
+            let distance = (tx as f32 + 0.5 - middle).hypot(ty as f32 + 0.5 - middle);
+            let shade = if distance < reach && grain(tx, ty) > 0.55 {
+                40
+            } else {
+                255
+            };
+            data.extend([shade, shade, shade, 255]);
+        }
+    }
+    data
+}
+
+/// per texel white noise 0..1
+fn grain(x: u32, y: u32) -> f32 {
+    let mut hash = x.wrapping_mul(0x27d4_eb2d) ^ y.wrapping_mul(0x1656_67b1);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    (hash & 0xffff) as f32 / 0xffff as f32
+}
+
+#[test]
+fn test_procedural_texture() {
+    let stone = procedural_texture(BlockType::Stone, Direction::Up, 16);
+    assert_eq!(stone.len(), 16 * 16 * PIXEL_SIZE);
+    assert!(stone.chunks(PIXEL_SIZE).all(|pixel| pixel[3] == 255));
+    // there is some noise in it
+    assert!(stone.chunks(PIXEL_SIZE).any(|pixel| pixel != &stone[..4]));
+    // it always comes out the same
+    assert_eq!(
+        stone,
+        procedural_texture(BlockType::Stone, Direction::Up, 16)
+    );
+    assert_ne!(
+        stone,
+        procedural_texture(BlockType::Dirt, Direction::Up, 16)
+    );
+    // bigger textures keep the same 16x16 pattern
+    let big = procedural_texture(BlockType::Stone, Direction::Up, 32);
+    assert_eq!(big[..4], stone[..4]);
+    assert_eq!(big[4..8], stone[..4]);
+    // grass has a green edge on the sides
+    let side = procedural_texture(BlockType::Grass, Direction::Left, 16);
+    assert!(side[1] > side[0] && side[1] > side[2]);
+}